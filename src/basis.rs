@@ -0,0 +1,244 @@
+//! Saving and restoring the basis of a dictionary, to warm start the simplex.
+//!
+//! A basis only records which labels are basic (`ll`) and which are not
+//! (`lc`), so it can be loaded into any dictionary built from a program with
+//! the same variables and constraints, even if the data has changed.
+//!
+//! Bases can also be exchanged with other solvers in the MPS basis format
+//! (`.bas` files). There, the `k`-th line of the dictionary is the row `R<k>`
+//! and the variables keep the names of the `LinearProgram`.
+
+use std::fmt::{self, Display, Formatter};
+
+use linear_system::*;
+use parser::LinearProgram;
+use error::Error;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Basis {
+    pub ll: Vec<usize>, // basic labels
+    pub lc: Vec<usize>, // non-basic labels, the first one being the constant
+}
+
+impl Basis {
+    /// Parses a basis written by its `Display` implementation.
+    pub fn parse(src: &str) -> Result<Basis, Error> {
+        let mut ll = None;
+        let mut lc = None;
+        for (i, line) in src.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let labels = match words.next() {
+                Some("basic:") => &mut ll,
+                Some("nonbasic:") => &mut lc,
+                Some(w) => {
+                    let msg = format!("expected basic: or nonbasic:, found `{}`", w);
+                    return Err(Error::Parse(i + 1, msg));
+                },
+                None => continue,
+            };
+            let v: Result<Vec<usize>, _> = words.map(|w| w.parse()).collect();
+            *labels = Some(v.map_err(|_| Error::Parse(i + 1, "expected labels".to_string()))?);
+        }
+        match (ll, lc) {
+            (Some(ll), Some(lc)) => Ok(Basis { ll, lc }),
+            _ => {
+                let msg = "missing basic: or nonbasic: line".to_string();
+                Err(Error::Parse(src.lines().count(), msg))
+            },
+        }
+    }
+}
+
+/// Label of the variable or row called `name` in a `.bas` file, `m` being the
+/// number of rows.
+fn bas_label(lp: &LinearProgram, m: usize, name: &str) -> Option<usize> {
+    let n = lp.vars.len();
+    if let Some(j) = lp.vars.iter().position(|x| x == name) {
+        return Some(j + 1);
+    }
+    match name.strip_prefix('R').and_then(|k| k.parse::<usize>().ok()) {
+        Some(k) if k >= 1 && k <= m => Some(n + k),
+        _ => None,
+    }
+}
+
+/// Reads a basis in the MPS basis format for the dictionary given by
+/// `lp.to_dict()`. Variables which do not appear are non-basic, rows are basic.
+/// Since the variables have no upper bound in the dictionary, `UL` and `LL`
+/// records both mean that the variable is non-basic.
+pub fn read_bas(lp: &LinearProgram, src: &str) -> Result<Basis, Error> {
+    let n = lp.vars.len();
+    let m = lp.row_kinds()?.len();
+    let mut basic: Vec<bool> = init_zero_vec(n + m + 1, false);
+    for b in basic[n+1..].iter_mut() {
+        *b = true;
+    }
+
+    for (lineno, line) in src.lines().enumerate() {
+        if line.starts_with('*') || line.trim().is_empty() { continue; }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let label = |k: usize| words.get(k)
+            .and_then(|w| bas_label(lp, m, w))
+            .ok_or_else(|| Error::Parse(lineno + 1, format!("unknown variable or row in `{}`", line.trim())));
+        match words[0] {
+            "NAME" => (),
+            "ENDATA" => break,
+            "XU" | "XL" => {
+                let (col, row) = (label(1)?, label(2)?);
+                basic[col] = true;
+                basic[row] = false;
+            },
+            "UL" | "LL" => {
+                let col = label(1)?;
+                basic[col] = false;
+            },
+            _ => return Err(Error::Parse(lineno + 1, format!("unknown record `{}`", words[0]))),
+        }
+    }
+
+    Ok(Basis {
+        ll: (1..n+m+1).filter(|&l| basic[l]).collect(),
+        lc: (0..n+m+1).filter(|&l| l == 0 || !basic[l]).collect(),
+    })
+}
+
+/// Writes `b`, a basis of the dictionary given by `lp.to_dict()`, in the MPS
+/// basis format. Each basic variable is paired with a non-basic row, which is
+/// at its upper bound for `<=` rows and at its lower bound for `>=` rows.
+pub fn write_bas(lp: &LinearProgram, b: &Basis, name: &str) -> Result<String, Error> {
+    let n = lp.vars.len();
+    let kinds = lp.row_kinds()?;
+    let row_name = |l: usize| format!("R{}", l - n);
+    let cols = b.ll.iter().filter(|&&l| l <= n);
+    let rows = b.lc.iter().filter(|&&l| l > n);
+
+    let mut res = format!("NAME          {}\n", name);
+    for (&col, &row) in cols.zip(rows) {
+        let rec = if kinds[row - n - 1] == OrderRel::GT { "XL" } else { "XU" };
+        res.push_str(&format!(" {} {:<8} {}\n", rec, lp.vars[col - 1], row_name(row)));
+    }
+    res.push_str("ENDATA\n");
+    Ok(res)
+}
+
+impl Display for Basis {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "basic:")?;
+        for l in self.ll.iter() {
+            write!(f, " {}", l)?;
+        }
+        write!(f, "\nnonbasic:")?;
+        for l in self.lc.iter() {
+            write!(f, " {}", l)?;
+        }
+        writeln!(f)
+    }
+}
+
+impl<F: OrdField> Dictionary<F> {
+    pub fn basis(&self) -> Basis {
+        Basis {
+            ll: self.ll.clone(),
+            lc: self.lc.clone(),
+        }
+    }
+
+    /// Pivots until the basic variables are the ones of `b`. The dictionary
+    /// is left untouched if `b` is not a basis of it.
+    /// The resulting dictionary may not be feasible, in which case
+    /// `run_simplex` starts with the first phase.
+    pub fn set_basis(&mut self, b: &Basis) -> Result<(), Error> {
+        if b.ll.len() != self.h() || b.lc.len() != self.w() {
+            return Err(Error::Model(format!("the basis has {} basic and {} non-basic variables instead of {} and {}",
+                                            b.ll.len(), b.lc.len(), self.h(), self.w())));
+        }
+        let mut labels: Vec<usize> = b.ll.iter().chain(b.lc.iter()).cloned().collect();
+        let mut ours: Vec<usize> = self.ll.iter().chain(self.lc.iter()).cloned().collect();
+        labels.sort();
+        ours.sort();
+        if labels != ours {
+            return Err(Error::Model("the basis does not have the same variables as the program".to_string()));
+        }
+        if b.lc[0] != self.lc[0] {
+            return Err(Error::Model(format!("x_{} must be the constant term", self.lc[0])));
+        }
+
+        let mut d = self.clone();
+        for &label in b.ll.iter() {
+            let je = match d.lc.iter().position(|&x| x == label) {
+                Some(je) => je,
+                None => continue, // already basic
+            };
+            let mut best = None;
+            for i in 0..d.h() {
+                if b.ll.contains(&d.ll[i]) { continue; }
+                let a = abs(d.m.at(i, je));
+                if a != F::zero() && best.is_none_or(|(_, max)| a > max) {
+                    best = Some((i, a));
+                }
+            }
+            match best {
+                Some((il, _)) => d.perform_pivot(je, il),
+                None => return Err(Error::Numerical("the basis is singular".to_string())),
+            }
+        }
+        *self = d;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    #[test]
+    fn test_parse() {
+        let b = Basis { ll: vec![2, 4], lc: vec![0, 1, 3] };
+        assert_eq!(Basis::parse(&b.to_string()).unwrap(), b);
+        assert!(Basis::parse("basic: 1 x\nnonbasic: 0").is_err());
+    }
+
+    #[test]
+    fn test_warm_start() {
+        let mut d = make_dict();
+        d.run_simplex(Heuristic::Bland, false);
+        let b = d.basis();
+
+        let mut d = make_dict();
+        d.set_basis(&b).unwrap();
+        assert!(matches!(d.find_entering_variable(), Step::Finished));
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(24.0));
+    }
+
+    #[test]
+    fn test_infeasible_basis() {
+        // x_1 = -8 + x_3 - 2 x_2 once x_1 replaces x_3 in the basis
+        let mut d = make_dict();
+        d.set_basis(&Basis { ll: vec![1, 4], lc: vec![0, 3, 2] }).unwrap();
+        assert!((0..d.h()).any(|i| d.m.at(i, 0) < 0.0));
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(24.0));
+    }
+
+    #[test]
+    fn test_bas_format() {
+        let lp = Parser::parse_lp("MAXIMIZE 3 x + 8 y\nSUBJECT TO\n-x - 2 y <= 8\n\
+                                       3 x + 4 y <= 12\nBOUNDS\nVARIABLES\nx\ny\n").unwrap();
+        let mut d = lp.to_dict().unwrap();
+        d.run_simplex(Heuristic::Bland, false);
+        let bas = write_bas(&lp, &d.basis(), "test").unwrap();
+        assert_eq!(bas, "NAME          test\n XU y        R2\nENDATA\n");
+
+        let mut b = read_bas(&lp, &bas).unwrap();
+        b.ll.sort();
+        assert_eq!(b, Basis { ll: vec![2, 3], lc: vec![0, 1, 4] });
+        assert!(read_bas(&lp, " XU z R1").is_err());
+    }
+
+    #[test]
+    fn test_wrong_basis() {
+        let mut d = make_dict();
+        assert!(d.set_basis(&Basis { ll: vec![1, 5], lc: vec![0, 3, 2] }).is_err());
+        assert_eq!(d, make_dict());
+    }
+}
@@ -0,0 +1,271 @@
+//! Branch-and-bound for programs with integer variables.
+//!
+//! Each node is a solved dictionary of the relaxation. Branching on a variable
+//! with a fractional value `v` adds the constraint `x <= floor(v)` to one child
+//! and `x >= ceil(v)` to the other, which are re-optimized from the basis of
+//! their parent with the dual simplex.
+
+use std::f64;
+
+use linear_system::*;
+use gomory;
+use heuristics::PrimalHeuristics;
+use error::Error;
+
+/// Values closer than this to an integer are considered integral.
+pub const INT_TOL: f64 = 1e-6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NodeSelection {
+    BestBound,
+    DepthFirst,
+}
+
+#[derive(Clone, Copy)]
+pub struct BranchAndBound {
+    pub heur: Heuristic,
+    pub selection: NodeSelection,
+    pub max_nodes: usize,
+    pub gap_tol: f64, // relative gap under which the search stops
+    pub root_cuts: usize, // rounds of Gomory mixed-integer cuts at the root
+    pub heuristics: Option<PrimalHeuristics>, // run at the root for a first incumbent
+}
+
+#[derive(Clone, Debug)]
+pub struct MipSolution {
+    pub result: SolveResult<f64>,
+    pub values: Vec<f64>, // value of each variable of the incumbent, indexed by label
+    pub bound: f64, // upper bound on the optimum
+    pub nodes: usize,
+    pub limit_reached: bool,
+    pub heuristic: Option<f64>, // objective of the solution found by the heuristics
+}
+
+impl MipSolution {
+    /// Relative gap between the incumbent and the bound.
+    pub fn gap(&self) -> f64 {
+        match self.result {
+            SolveResult::Optimal(z) => (self.bound - z).abs() / z.abs().max(1.0),
+            _ => f64::INFINITY,
+        }
+    }
+}
+
+struct Node {
+    d: Dictionary<f64>,
+    bound: f64,
+}
+
+/// The integer variable whose value is the farthest from an integer.
+pub fn most_fractional(values: &[f64], integers: &[usize]) -> Option<(usize, f64)> {
+    let mut res = None;
+    let mut max = INT_TOL;
+    for &label in integers.iter() {
+        let v = values[label];
+        let frac = (v - v.round()).abs();
+        if frac > max {
+            max = frac;
+            res = Some((label, v));
+        }
+    }
+    res
+}
+
+impl BranchAndBound {
+    pub fn new() -> BranchAndBound {
+        BranchAndBound {
+            heur: Heuristic::Dumb,
+            selection: NodeSelection::BestBound,
+            max_nodes: 100_000,
+            gap_tol: 1e-9,
+            root_cuts: 0,
+            heuristics: Some(PrimalHeuristics::new()),
+        }
+    }
+
+    fn select(&self, open: &mut Vec<Node>) -> Option<Node> {
+        match self.selection {
+            NodeSelection::DepthFirst => open.pop(),
+            NodeSelection::BestBound => {
+                let mut best = None;
+                for (k, node) in open.iter().enumerate() {
+                    if best.is_none_or(|(_, b)| node.bound > b) {
+                        best = Some((k, node.bound));
+                    }
+                }
+                best.map(|(k, _)| open.swap_remove(k))
+            },
+        }
+    }
+
+    fn is_pruned(&self, bound: f64, incumbent: &Option<(f64, Vec<f64>)>) -> bool {
+        match *incumbent {
+            Some((z, _)) => bound - z <= self.gap_tol * z.abs().max(1.0),
+            None => false,
+        }
+    }
+
+    /// Maximizes the objective of `d` with the variables of `integers` integral.
+    pub fn solve(&self, d: &Dictionary<f64>, integers: &[usize]) -> Result<MipSolution, Error> {
+        let mut root = d.clone();
+        let mut cut_integers = integers.to_vec();
+        cut_integers.extend(gomory::integral_slacks(d, integers));
+        let (root_res, _) = gomory::cutting_plane(&mut root, &mut cut_integers,
+                                                  gomory::CutKind::MixedInteger, self.root_cuts,
+                                                  self.heur, false)?;
+        let bound = match root_res {
+            SolveResult::Optimal(z) => z,
+            r => return Ok(MipSolution {
+                result: r,
+                values: Vec::new(),
+                bound: f64::INFINITY,
+                nodes: 1,
+                limit_reached: false,
+                heuristic: None,
+            }),
+        };
+
+        let mut incumbent = self.heuristics.and_then(|h| h.run(d, &root, integers));
+        let heuristic = incumbent.as_ref().map(|&(z, _)| z);
+        let mut open = vec![Node { d: root, bound }];
+        let mut nodes = 0;
+        let mut limit_reached = false;
+        while let Some(node) = self.select(&mut open) {
+            if self.is_pruned(node.bound, &incumbent) { continue; }
+            if nodes >= self.max_nodes {
+                open.push(node);
+                limit_reached = true;
+                break;
+            }
+            nodes += 1;
+
+            let mut values = node.d.values();
+            let (label, v) = match most_fractional(&values, integers) {
+                Some(x) => x,
+                None => {
+                    for &label in integers.iter() {
+                        values[label] = values[label].round();
+                    }
+                    incumbent = Some((node.bound, values));
+                    continue;
+                },
+            };
+            for &(rel, rhs) in [(OrderRel::LT, v.floor()), (OrderRel::GT, v.ceil())].iter() {
+                let mut child = node.d.clone();
+                child.add_constraint(&[(label, 1.0)], rel, rhs)?;
+                if let SolveResult::Optimal(z) = child.reoptimize(self.heur) {
+                    if !self.is_pruned(z, &incumbent) {
+                        open.push(Node { d: child, bound: z });
+                    }
+                }
+            }
+        }
+
+        let open_bound = open.iter().fold(f64::NEG_INFINITY, |b, node| b.max(node.bound));
+        Ok(match incumbent {
+            Some((z, values)) => MipSolution {
+                result: SolveResult::Optimal(z),
+                values,
+                bound: open_bound.max(z),
+                nodes,
+                limit_reached,
+                heuristic,
+            },
+            None => MipSolution {
+                result: SolveResult::Infeasible,
+                values: Vec::new(),
+                bound: open_bound,
+                nodes,
+                limit_reached,
+                heuristic,
+            },
+        })
+    }
+}
+
+impl Default for BranchAndBound {
+    fn default() -> BranchAndBound {
+        BranchAndBound::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    fn knapsack() -> (Dictionary<f64>, Vec<usize>) {
+        let lp = make_knapsack();
+        (lp.to_dict().unwrap(), lp.integer_labels().unwrap())
+    }
+
+    #[test]
+    fn test_best_bound() {
+        let (d, integers) = knapsack();
+        let sol = BranchAndBound::new().solve(&d, &integers).unwrap();
+        assert_eq!(sol.result, SolveResult::Optimal(20.0));
+        assert!((sol.values[1] - 4.0).abs() < 1e-9 && sol.values[2].abs() < 1e-9);
+        assert_eq!(sol.gap(), 0.0);
+    }
+
+    #[test]
+    fn test_depth_first() {
+        let (d, integers) = knapsack();
+        let mut bb = BranchAndBound::new();
+        bb.selection = NodeSelection::DepthFirst;
+        let sol = bb.solve(&d, &integers).unwrap();
+        assert_eq!(sol.result, SolveResult::Optimal(20.0));
+        assert!(!sol.limit_reached);
+    }
+
+    #[test]
+    fn test_root_cuts() {
+        let (d, integers) = knapsack();
+        let mut bb = BranchAndBound::new();
+        bb.root_cuts = 2;
+        let sol = bb.solve(&d, &integers).unwrap();
+        assert!((sol.result.optimum().unwrap() - 20.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_no_heuristics() {
+        let (d, integers) = knapsack();
+        let mut bb = BranchAndBound::new();
+        bb.heuristics = None;
+        let sol = bb.solve(&d, &integers).unwrap();
+        assert_eq!(sol.result, SolveResult::Optimal(20.0));
+        assert_eq!(sol.heuristic, None);
+    }
+
+    #[test]
+    fn test_binary() {
+        let lp = Parser::parse_lp("MAXIMIZE 8 a + 11 b + 6 c + 4 d\nSUBJECT TO\n\
+                                       5 a + 7 b + 4 c + 3 d <= 14\nBOUNDS\nBINARY\na b c d\n\
+                                       VARIABLES\na\nb\nc\nd\n").unwrap();
+        let d = lp.to_dict().unwrap();
+        let sol = BranchAndBound::new().solve(&d, &lp.integer_labels().unwrap()).unwrap();
+        let opt = sol.result.optimum().unwrap();
+        assert!((opt - 21.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fractional_bounds() {
+        // Branching on x - 0.5 would give x = 3.5
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx <= 3.7\nBOUNDS\nx >= 0.5\n\
+                                       GENERAL\nx\nVARIABLES\nx\n").unwrap();
+        let sf = ::standard_form::standard_form(&lp).unwrap();
+        let sol = BranchAndBound::new().solve(&sf.lp.to_dict(), &lp.integer_labels().unwrap())
+            .unwrap();
+        assert!((sol.result.optimum().unwrap() - 3.0).abs() < 1e-9);
+        assert!((sol.values[1] + sf.shift(1) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_infeasible() {
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\n2 x >= 1\n2 x <= 1.5\n\
+                                       BOUNDS\nGENERAL\nx\nVARIABLES\nx\n").unwrap();
+        let d = lp.to_dict().unwrap();
+        let sol = BranchAndBound::new().solve(&d, &lp.integer_labels().unwrap()).unwrap();
+        assert_eq!(sol.result, SolveResult::Infeasible);
+    }
+}
@@ -0,0 +1,180 @@
+//! Crossover from a non-basic solution (typically the one found by the
+//! interior-point method) to an optimal basis.
+//!
+//! The primal and dual vectors are indexed by variable label, like the ones of
+//! `IpmSolution`. The basis is recovered in three phases:
+//!
+//! - basis guess: the variables with a positive value enter the basis in place
+//!   of variables which are nil, which does not move the point;
+//! - primal push: the remaining non-basic variables with a positive value
+//!   (the superbasic ones) are moved to zero, pivoting when a basic variable
+//!   reaches zero first;
+//! - dual push: basic variables with a positive reduced cost leave the basis
+//!   by degenerate pivots.
+//!
+//! A few iterations of the simplex then clean up the basis.
+
+use linear_system::*;
+use error::Error;
+
+/// Values below this are considered nil.
+const CROSSOVER_TOL: f64 = 1e-7;
+
+/// Smallest acceptable pivot element.
+const PIVOT_TOL: f64 = 1e-9;
+
+fn value(v: &[f64], label: usize) -> f64 {
+    if label < v.len() { v[label] } else { 0.0 }
+}
+
+/// Line of the basic variable which is nil in `x` with the largest
+/// coefficient (in absolute value) in column `j`.
+fn best_nil_line(d: &Dictionary<f64>, x: &[f64], j: usize) -> Option<usize> {
+    let mut res = None;
+    let mut max = PIVOT_TOL;
+    for i in 0..d.h() {
+        let a = d.m.at(i, j).abs();
+        if value(x, d.ll[i]) <= CROSSOVER_TOL && a > max {
+            max = a;
+            res = Some(i);
+        }
+    }
+    res
+}
+
+fn basis_guess(d: &mut Dictionary<f64>, x: &[f64]) {
+    let mut cands: Vec<usize> = d.lc[1..].iter()
+        .cloned()
+        .filter(|&label| value(x, label) > CROSSOVER_TOL)
+        .collect();
+    cands.sort_by(|&a, &b| value(x, b).total_cmp(&value(x, a)));
+
+    for label in cands {
+        let j = d.lc.iter().position(|&l| l == label).unwrap();
+        if let Some(i) = best_nil_line(d, x, j) {
+            d.perform_pivot(j, i);
+        }
+    }
+}
+
+fn primal_push(d: &mut Dictionary<f64>, x: &[f64]) {
+    // Values of the non-basic variables, then of the basic ones
+    let mut v_n: Vec<f64> = d.lc.iter()
+        .map(|&label| if value(x, label) > CROSSOVER_TOL { value(x, label) } else { 0.0 })
+        .collect();
+    v_n[0] = 1.0;
+    let mut v_b: Vec<f64> = (0..d.h())
+        .map(|i| (0..d.w()).map(|j| d.m.at(i, j) * v_n[j]).sum())
+        .collect();
+
+    for (j, v_j) in v_n.iter_mut().enumerate().skip(1) {
+        let t = *v_j;
+        if t == 0.0 { continue; }
+
+        // Decreasing the variable by `t` decreases the basic ones by `m[i, j] t`
+        let mut step = t;
+        let mut blocking = None;
+        for (i, &v_i) in v_b.iter().enumerate() {
+            let a = d.m.at(i, j);
+            if a > PIVOT_TOL {
+                let lim = v_i.max(0.0) / a;
+                if lim < step {
+                    step = lim;
+                    blocking = Some(i);
+                }
+            }
+        }
+
+        for (i, v_i) in v_b.iter_mut().enumerate() {
+            *v_i -= d.m.at(i, j) * step;
+        }
+        *v_j = 0.0;
+        if let Some(i) = blocking {
+            v_b[i] = t - step;
+            d.perform_pivot(j, i);
+        }
+    }
+}
+
+fn dual_push(d: &mut Dictionary<f64>, z: &[f64]) {
+    for i in 0..d.h() {
+        if value(z, d.ll[i]) <= CROSSOVER_TOL || d.m.at(i, 0) > CROSSOVER_TOL {
+            continue;
+        }
+        let mut best = None;
+        let mut max = PIVOT_TOL;
+        for j in 1..d.w() {
+            let a = d.m.at(i, j).abs();
+            if value(z, d.lc[j]) <= CROSSOVER_TOL && a > max {
+                max = a;
+                best = Some(j);
+            }
+        }
+        if let Some(j) = best {
+            d.perform_pivot(j, i);
+        }
+    }
+}
+
+/// Rounds to zero the slightly negative values of the basic variables, which
+/// come from the tolerance of the interior solution.
+fn clean(d: &mut Dictionary<f64>) {
+    for i in 0..d.h() {
+        let b = d.m.at(i, 0);
+        if b < 0.0 && b > -CROSSOVER_TOL {
+            d.m.set_at(i, 0, 0.0);
+        }
+    }
+}
+
+/// Builds an optimal basis of `d` from the primal values `x` and the reduced
+/// costs `z`, and returns the resulting dictionary. Fails if they are not
+/// finite.
+pub fn crossover(d: &Dictionary<f64>, x: &[f64], z: &[f64],
+                 heur: Heuristic, latex: bool) -> Result<(Dictionary<f64>, SolveResult<f64>), Error> {
+    if let Some(v) = x.iter().chain(z.iter()).find(|v| !v.is_finite()) {
+        return Err(Error::Numerical(format!("the interior solution has the value {}", v)));
+    }
+    let mut res = d.clone();
+    basis_guess(&mut res, x);
+    primal_push(&mut res, x);
+    dual_push(&mut res, z);
+    clean(&mut res);
+    let r = res.run_simplex(heur, latex);
+    Ok((res, r))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use interior_point::InteriorPoint;
+
+    #[test]
+    fn test_crossover_ipm() {
+        let d = make_dict();
+        let sol = InteriorPoint::new().solve(&d);
+        let (res, r) = crossover(&d, &sol.x, &sol.z, Heuristic::Bland, false).unwrap();
+        res.check_integrity().unwrap();
+        assert_eq!(r, SolveResult::Optimal(24.0));
+        let i = res.ll.iter().position(|&l| l == 2).unwrap();
+        assert!((res.m.at(i, 0) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_crossover_face() {
+        // max x1 + x2 st x1 + x2 <= 2, the interior solution is in the middle of the face
+        let d = Dictionary {
+            m: Matrix { h: 1, w: 3, m: vec![2., -1., -1.] },
+            ll: vec![3],
+            lc: vec![0, 1, 2],
+            obj: vec![0., 1., 1.],
+            weq: vec![0., 0., 0.],
+            var_name: "x",
+        };
+        let (res, r) = crossover(&d, &[0., 1., 1., 0.], &[0., 0., 0., 1.], Heuristic::Bland, false).unwrap();
+        assert_eq!(r, SolveResult::Optimal(2.0));
+        assert_eq!(res.ll, vec![1]);
+        assert_eq!(res.m.at(0, 0), 2.0);
+        assert!(crossover(&d, &[0., 1., f64::NAN, 0.], &[0., 0., 0., 1.], Heuristic::Bland, false).is_err());
+    }
+}
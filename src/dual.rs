@@ -0,0 +1,159 @@
+//! The dual of a linear program.
+//!
+//! The primal is first written as `max c x` subject to `A x <= b`: the `>=`
+//! rows are negated, equalities are split into two rows, and the bounds become
+//! rows too. A variable with a negative lower bound is free, the bound being a
+//! row. The dual is then `min b y` subject to `A^T y >= c` and `y >= 0`, the
+//! row of a free variable being an equality, split in two rows since the
+//! dictionary only accepts inequalities. The dual of a minimization is written
+//! as the maximization of `-b y`, so that both programs have the same optimum.
+//!
+//! The dual variables are named after the rows: `y<k>` for the `k`-th
+//! constraint (`y<k>a` and `y<k>b` for an equality), `u_<x>` and `l_<x>` for
+//! the upper and lower bounds of `x`. Integrality constraints are ignored.
+
+use std::collections::HashMap;
+
+use linear_system::*;
+use parser::{LinearProgram, PInequation};
+use error::Error;
+
+/// Row `sum a x <= b` of the primal, with the name of its dual variable.
+struct Row {
+    coeffs: Vec<(f64, usize)>,
+    cst: f64,
+    name: String,
+}
+
+fn negate(coeffs: &[(f64, usize)]) -> Vec<(f64, usize)> {
+    coeffs.iter().map(|&(a, j)| (-a, j)).collect()
+}
+
+pub fn dual(lp: &LinearProgram) -> Result<LinearProgram, Error> {
+    let n = lp.vars.len();
+    let idx: HashMap<&str, usize> = lp.vars.iter().enumerate().map(|(j, x)| (&x[..], j)).collect();
+    let find = |x: &str| idx.get(x).cloned().ok_or_else(|| Error::Model(format!("unknown variable {}", x)));
+
+    let mut rows = Vec::new();
+    for (k, ineq) in lp.ineqs.iter().enumerate() {
+        let coeffs = ineq.prods.iter()
+            .map(|&(a, ref x)| find(x).map(|j| (a, j)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let name = format!("y{}", k + 1);
+        match ineq.kind {
+            OrderRel::LT => rows.push(Row { coeffs, cst: ineq.cst, name }),
+            OrderRel::GT => rows.push(Row { coeffs: negate(&coeffs), cst: -ineq.cst, name }),
+            OrderRel::EQ => {
+                rows.push(Row { coeffs: coeffs.clone(), cst: ineq.cst, name: format!("{}a", name) });
+                rows.push(Row { coeffs: negate(&coeffs), cst: -ineq.cst, name: format!("{}b", name) });
+            },
+        }
+    }
+
+    let mut free = init_zero_vec(n, false);
+    for b in lp.bounds.iter() {
+        let j = find(&b.var)?;
+        if let Some(u) = b.upper {
+            rows.push(Row { coeffs: vec![(1.0, j)], cst: u, name: format!("u_{}", b.var) });
+        }
+        match b.lower {
+            Some(l) if l != 0.0 => {
+                free[j] = free[j] || l < 0.0;
+                rows.push(Row { coeffs: vec![(-1.0, j)], cst: -l, name: format!("l_{}", b.var) });
+            },
+            _ => (),
+        }
+    }
+
+    let mult = match lp.goal {
+        Maximize => 1.0,
+        Minimize => -1.0,
+    };
+    let mut c: Vec<f64> = init_zero_vec(n, 0.0);
+    for &(c_j, ref x) in lp.obj.iter() {
+        c[find(x)?] += mult * c_j;
+    }
+
+    // Column `j` of the primal becomes the row `sum a_ij y_i >= c_j`
+    let mut cols: Vec<Vec<(f64, String)>> = vec![Vec::new(); n];
+    for row in rows.iter() {
+        for &(a, j) in row.coeffs.iter() {
+            cols[j].push((a, row.name.clone()));
+        }
+    }
+    let mut ineqs = Vec::new();
+    for (j, prods) in cols.into_iter().enumerate() {
+        if free[j] {
+            ineqs.push(PInequation { prods: prods.clone(), kind: OrderRel::LT, cst: c[j] });
+        }
+        ineqs.push(PInequation { prods, kind: OrderRel::GT, cst: c[j] });
+    }
+
+    let obj = rows.iter()
+        .filter(|row| row.cst != 0.0)
+        .map(|row| (mult * row.cst, row.name.clone()))
+        .collect();
+    let goal = match lp.goal {
+        Maximize => Minimize,
+        Minimize => Maximize,
+    };
+    let vars = rows.into_iter().map(|row| row.name).collect();
+    Ok(LinearProgram::new(goal, obj, ineqs, Vec::new(), Vec::new(), vars))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    /// Optimum of `lp` in its own sense.
+    fn optimum(lp: &LinearProgram) -> f64 {
+        let z = lp.clone().to_dict().unwrap().run_simplex(Heuristic::Bland, false).optimum().unwrap();
+        match lp.goal {
+            Maximize => z,
+            Minimize => -z,
+        }
+    }
+
+    #[test]
+    fn test_dual() {
+        let lp = Parser::parse_lp("MAXIMIZE 3 x + 8 y\nSUBJECT TO\nx + 2 y <= 8\n3 x + 4 y <= 12\n\
+                                   BOUNDS\nVARIABLES\nx\ny\n").unwrap();
+        let d = dual(&lp).unwrap();
+        let expected = Parser::parse_lp("MINIMIZE 8 y1 + 12 y2\nSUBJECT TO\ny1 + 3 y2 >= 3\n\
+                                         2 y1 + 4 y2 >= 8\nBOUNDS\nVARIABLES\ny1\ny2\n").unwrap();
+        assert_eq!(d, expected);
+        assert_eq!(optimum(&d), 24.0);
+    }
+
+    #[test]
+    fn test_rows_and_bounds() {
+        // The optimum is 2, at x = 4/3, y = 2/3
+        let lp = Parser::parse_lp("MINIMIZE x + y\nSUBJECT TO\nx + y >= 2\nx - 2 y = 0\n\
+                                   BOUNDS\n0 <= y <= 5\nVARIABLES\nx\ny\n").unwrap();
+        let d = dual(&lp).unwrap();
+        assert_eq!(d.vars, vec!["y1", "y2a", "y2b", "u_y"]);
+        assert!((optimum(&d) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dual_of_dual() {
+        let lp = Parser::parse_lp("MAXIMIZE x + 2 y\nSUBJECT TO\nx + y <= 4\nx - y >= -2\n\
+                                   BOUNDS\nVARIABLES\nx\ny\n").unwrap();
+        let dd = dual(&dual(&lp).unwrap()).unwrap();
+        assert_eq!(optimum(&dd), optimum(&lp));
+        assert!(dual(&Parser::parse_lp("MAXIMIZE z\nSUBJECT TO\nBOUNDS\nVARIABLES\nx\n").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_write_dual() {
+        // All the constants are 0, so the dual has no objective
+        let lp = Parser::parse_lp("MAXIMIZE x - y\nSUBJECT TO\nx - y <= 0\nBOUNDS\nVARIABLES\nx\ny\n")
+            .unwrap();
+        let d = dual(&lp).unwrap();
+        assert!(d.obj.is_empty());
+        let read = Parser::parse_lp(&d.to_string()).unwrap();
+        assert_eq!((&read.ineqs, &read.vars), (&d.ineqs, &d.vars));
+        assert_eq!(optimum(&read), optimum(&lp));
+    }
+}
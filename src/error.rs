@@ -0,0 +1,71 @@
+//! The ways reading and solving a program can fail, each with its own exit
+//! code in the command-line tool.
+//!
+//! An infeasible or unbounded program is not an error: it is a `SolveResult`.
+
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    Usage(String), // wrong command-line arguments, or a call the library refuses
+    Io(String, io::Error), // the file which could not be read or written
+    Parse(usize, String), // line of the file, starting at 1
+    Model(String), // the program is not valid, e.g. it uses an undeclared variable
+    Numerical(String), // the computation failed, e.g. on a singular basis
+}
+
+impl Error {
+    /// Exit code of the command-line tool, 0 being a success.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Usage(_) => 1,
+            Error::Io(..) => 2,
+            Error::Parse(..) => 3,
+            Error::Model(_) => 4,
+            Error::Numerical(_) => 5,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Usage(ref msg) => write!(f, "{}", msg),
+            Error::Io(ref path, ref why) => write!(f, "{}: {}", path, why),
+            Error::Parse(line, ref msg) => write!(f, "line {}: {}", line, msg),
+            Error::Model(ref msg) => write!(f, "invalid program: {}", msg),
+            Error::Numerical(ref msg) => write!(f, "numerical failure: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(_, ref why) => Some(why),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let errors = [
+            Error::Usage("no file".to_string()),
+            Error::Io("a.lp".to_string(), io::Error::new(io::ErrorKind::NotFound, "not found")),
+            Error::Parse(3, "expected BOUNDS".to_string()),
+            Error::Model("unknown variable x".to_string()),
+            Error::Numerical("the basis is singular".to_string()),
+        ];
+        let codes: Vec<i32> = errors.iter().map(|e| e.exit_code()).collect();
+        assert_eq!(codes, vec![1, 2, 3, 4, 5]);
+        assert_eq!(errors[1].to_string(), "a.lp: not found");
+        assert_eq!(errors[2].to_string(), "line 3: expected BOUNDS");
+    }
+}
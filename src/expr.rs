@@ -0,0 +1,202 @@
+//! Linear expressions over the variables of a `Model`, so that models can be
+//! written with operators:
+//!
+//! ```
+//! use simplex::model::Model;
+//!
+//! let mut m = Model::new();
+//! let x = m.add_var("x", 0.0, Some(3.0));
+//! let y = m.add_var("y", 0.0, None);
+//! m.add((x + 2.0 * y).le(8.0));
+//! m.maximize(3.0 * x + 8.0 * y);
+//! assert_eq!(m.solve().unwrap().objective(), Some(32.0));
+//! ```
+
+use std::iter::Sum;
+use std::ops::{Add, Sub, Mul, Neg};
+
+use linear_system::OrderRel;
+use model::Var;
+
+/// `sum a x + cst`, each variable appearing at most once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinExpr {
+    pub prods: Vec<(f64, Var)>,
+    pub cst: f64,
+}
+
+/// `sum a x (kind) cst`, as given to `Model::add`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinConstraint {
+    pub prods: Vec<(f64, Var)>,
+    pub kind: OrderRel,
+    pub cst: f64,
+}
+
+impl LinExpr {
+    pub fn zero() -> LinExpr {
+        LinExpr { prods: Vec::new(), cst: 0.0 }
+    }
+
+    fn add_term(&mut self, a: f64, x: Var) {
+        match self.prods.iter().position(|&(_, y)| y == x) {
+            Some(k) => {
+                self.prods[k].0 += a;
+                if self.prods[k].0 == 0.0 {
+                    self.prods.remove(k);
+                }
+            },
+            None if a != 0.0 => self.prods.push((a, x)),
+            None => (),
+        }
+    }
+
+    fn scale(mut self, k: f64) -> LinExpr {
+        for p in self.prods.iter_mut() {
+            p.0 *= k;
+        }
+        self.prods.retain(|&(a, _)| a != 0.0);
+        self.cst *= k;
+        self
+    }
+
+    fn compare<R: Into<LinExpr>>(self, kind: OrderRel, rhs: R) -> LinConstraint {
+        let e = self - rhs.into();
+        LinConstraint { prods: e.prods, kind, cst: -e.cst }
+    }
+
+    /// The constraint `self <= rhs`.
+    pub fn le<R: Into<LinExpr>>(self, rhs: R) -> LinConstraint {
+        self.compare(OrderRel::LT, rhs)
+    }
+
+    /// The constraint `self >= rhs`.
+    pub fn ge<R: Into<LinExpr>>(self, rhs: R) -> LinConstraint {
+        self.compare(OrderRel::GT, rhs)
+    }
+
+    /// The constraint `self = rhs`.
+    pub fn eq<R: Into<LinExpr>>(self, rhs: R) -> LinConstraint {
+        self.compare(OrderRel::EQ, rhs)
+    }
+}
+
+impl From<Var> for LinExpr {
+    fn from(x: Var) -> LinExpr {
+        LinExpr { prods: vec![(1.0, x)], cst: 0.0 }
+    }
+}
+
+impl From<f64> for LinExpr {
+    fn from(cst: f64) -> LinExpr {
+        LinExpr { prods: Vec::new(), cst }
+    }
+}
+
+impl<R: Into<LinExpr>> Add<R> for LinExpr {
+    type Output = LinExpr;
+    fn add(mut self, rhs: R) -> LinExpr {
+        let rhs = rhs.into();
+        for (a, x) in rhs.prods {
+            self.add_term(a, x);
+        }
+        self.cst += rhs.cst;
+        self
+    }
+}
+
+impl<R: Into<LinExpr>> Sub<R> for LinExpr {
+    type Output = LinExpr;
+    fn sub(self, rhs: R) -> LinExpr {
+        self + rhs.into().scale(-1.0)
+    }
+}
+
+impl Neg for LinExpr {
+    type Output = LinExpr;
+    fn neg(self) -> LinExpr {
+        self.scale(-1.0)
+    }
+}
+
+impl Mul<f64> for LinExpr {
+    type Output = LinExpr;
+    fn mul(self, k: f64) -> LinExpr {
+        self.scale(k)
+    }
+}
+
+impl Mul<LinExpr> for f64 {
+    type Output = LinExpr;
+    fn mul(self, e: LinExpr) -> LinExpr {
+        e.scale(self)
+    }
+}
+
+impl<R: Into<LinExpr>> Add<R> for Var {
+    type Output = LinExpr;
+    fn add(self, rhs: R) -> LinExpr {
+        LinExpr::from(self) + rhs
+    }
+}
+
+impl<R: Into<LinExpr>> Sub<R> for Var {
+    type Output = LinExpr;
+    fn sub(self, rhs: R) -> LinExpr {
+        LinExpr::from(self) - rhs
+    }
+}
+
+impl Neg for Var {
+    type Output = LinExpr;
+    fn neg(self) -> LinExpr {
+        -LinExpr::from(self)
+    }
+}
+
+impl Mul<f64> for Var {
+    type Output = LinExpr;
+    fn mul(self, k: f64) -> LinExpr {
+        LinExpr::from(self) * k
+    }
+}
+
+impl Mul<Var> for f64 {
+    type Output = LinExpr;
+    fn mul(self, x: Var) -> LinExpr {
+        self * LinExpr::from(x)
+    }
+}
+
+impl Sum for LinExpr {
+    fn sum<I: Iterator<Item=LinExpr>>(iter: I) -> LinExpr {
+        iter.fold(LinExpr::zero(), |acc, e| acc + e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use model::Model;
+
+    #[test]
+    fn test_operators() {
+        let mut m = Model::new();
+        let (x, y) = (m.add_var("x", 0.0, None), m.add_var("y", 0.0, None));
+        let e = 2.0 * x + y - 3.0 * (x - 1.0) + y * 0.5 - y;
+        assert_eq!(e, LinExpr { prods: vec![(-1.0, x), (0.5, y)], cst: 3.0 });
+        assert_eq!(-(x - x), LinExpr::zero());
+        let s: LinExpr = (1..4).map(|k| k as f64 * x).sum();
+        assert_eq!(s, 6.0 * x);
+    }
+
+    #[test]
+    fn test_constraints() {
+        let mut m = Model::new();
+        let (x, y) = (m.add_var("x", 0.0, None), m.add_var("y", 0.0, None));
+        assert_eq!((x + 2.0).le(5.0), LinConstraint { prods: vec![(1.0, x)], kind: OrderRel::LT, cst: 3.0 });
+        assert_eq!((2.0 * x).eq(y + 1.0),
+                   LinConstraint { prods: vec![(2.0, x), (-1.0, y)], kind: OrderRel::EQ, cst: 1.0 });
+        assert_eq!(LinExpr::from(y).ge(x).kind, OrderRel::GT);
+    }
+}
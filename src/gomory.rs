@@ -0,0 +1,278 @@
+//! Gomory cutting planes, derived from the lines of an optimal dictionary.
+//!
+//! A line `x_k = b + sum a_j x_j` where `x_k` is an integer variable with a
+//! fractional value `b` gives a cut that every integral solution satisfies but
+//! the current basic solution does not. With `f(x) = x - floor(x)`:
+//!
+//! - the fractional cut `sum f(-a_j) x_j >= f(b)` is valid when all the
+//!   non-basic variables are integers;
+//! - the mixed-integer cut `sum g_j x_j >= 1` is always valid, where `g_j` is
+//!   `f(-a_j) / f(b)` or `(1 - f(-a_j)) / (1 - f(b))` for integer variables, and
+//!   `-a_j / f(b)` or `a_j / (1 - f(b))` for continuous ones.
+//!
+//! Everything is generic on the field, so that the cuts can be computed exactly
+//! with rationals (see `rational`).
+
+use std::fmt::{self, Display, Formatter};
+
+use num::Integer;
+use num::rational::Ratio;
+
+use linear_system::*;
+use observer::{Observer, NoObserver, LatexObserver};
+use error::Error;
+use rational::is_unordered;
+pub use rational::Rational;
+
+pub trait Floor {
+    fn floor(self) -> Self;
+}
+
+impl Floor for f64 {
+    fn floor(self) -> f64 { f64::floor(self) }
+}
+
+impl<T: Clone + Integer> Floor for Ratio<T> {
+    fn floor(self) -> Ratio<T> { Ratio::floor(&self) }
+}
+
+fn frac<F: OrdField + Floor>(x: F) -> F {
+    x - x.floor()
+}
+
+fn is_integral<F: OrdField + Floor>(x: F) -> bool {
+    let f = frac(x);
+    f <= F::tolerance() || f >= F::one() - F::tolerance()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CutKind {
+    Fractional,
+    MixedInteger,
+}
+
+/// The constraint `sum coeff x_label >= rhs`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cut<F: OrdField> {
+    pub coeffs: Vec<(usize, F)>,
+    pub rhs: F,
+}
+
+impl<F: OrdField> Display for Cut<F> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let mut first = true;
+        for &(label, a) in self.coeffs.iter() {
+            if !first {
+                write!(f, " + ")?;
+            }
+            first = false;
+            write!(f, "{} x_{{ {} }}", a, label)?;
+        }
+        write!(f, r" \geq {}", self.rhs)
+    }
+}
+
+/// Cut from the line `i` of `d`, or `None` if its basic variable is continuous
+/// or integral, or if the cut is a fractional one and some non-basic variable
+/// is continuous.
+pub fn gomory_cut<F: OrdField + Floor>(d: &Dictionary<F>, i: usize, integers: &[usize],
+                                       kind: CutKind) -> Option<Cut<F>> {
+    let b = d.m.at(i, 0);
+    if !integers.contains(&d.ll[i]) || is_integral(b) {
+        return None;
+    }
+    let f0 = frac(b);
+    let one = F::one();
+    let mut coeffs = Vec::new();
+    for j in 1..d.w() {
+        let a = F::zero() - d.m.at(i, j);
+        if a == F::zero() { continue; }
+        let integer = integers.contains(&d.lc[j]);
+        let c = match kind {
+            CutKind::Fractional if !integer => return None,
+            CutKind::Fractional => frac(a),
+            CutKind::MixedInteger if integer => {
+                let fj = frac(a);
+                if fj <= f0 { fj / f0 } else { (one - fj) / (one - f0) }
+            },
+            CutKind::MixedInteger => {
+                if a >= F::zero() { a / f0 } else { (F::zero() - a) / (one - f0) }
+            },
+        };
+        if c != F::zero() {
+            coeffs.push((d.lc[j], c));
+        }
+    }
+    let rhs = match kind {
+        CutKind::Fractional => f0,
+        CutKind::MixedInteger => one,
+    };
+    Some(Cut { coeffs, rhs })
+}
+
+/// One cut for each line whose basic integer variable is fractional.
+pub fn gomory_cuts<F: OrdField + Floor>(d: &Dictionary<F>, integers: &[usize],
+                                        kind: CutKind) -> Vec<Cut<F>> {
+    (0..d.h()).filter_map(|i| gomory_cut(d, i, integers, kind)).collect()
+}
+
+/// Slack variables of the initial dictionary `d` which are integers whenever
+/// the variables of `integers` are: those of lines with integral coefficients
+/// on integer variables only.
+pub fn integral_slacks<F: OrdField + Floor>(d: &Dictionary<F>, integers: &[usize]) -> Vec<usize> {
+    (0..d.h())
+        .filter(|&i| (0..d.w()).all(|j| {
+            let a = d.m.at(i, j);
+            a == F::zero() || (is_integral(a) && (j == 0 || integers.contains(&d.lc[j])))
+        }))
+        .map(|i| d.ll[i])
+        .collect()
+}
+
+/// The fractional cuts if all the variables of `d` are integers, the mixed
+/// integer ones otherwise.
+pub fn best_cut_kind<F: OrdField>(d: &Dictionary<F>, integers: &[usize]) -> CutKind {
+    if d.lc[1..].iter().chain(d.ll.iter()).all(|l| integers.contains(l)) {
+        CutKind::Fractional
+    } else {
+        CutKind::MixedInteger
+    }
+}
+
+/// Solves `d` with cutting planes only: the relaxation is solved, then cuts
+/// are added and the dictionary re-optimized until the solution is integral,
+/// or for `max_rounds` rounds. `integers` must contain the integer variables
+/// of `d` (see `integral_slacks`), the slacks of the fractional cuts are added
+/// to it. Returns the result of the last relaxation and the number of rounds.
+pub fn cutting_plane<F: OrdField + Floor>(d: &mut Dictionary<F>, integers: &mut Vec<usize>,
+                                          kind: CutKind, max_rounds: usize,
+                                          heur: Heuristic, latex: bool) -> Result<(SolveResult<F>, usize), Error> {
+    if latex {
+        cutting_plane_with(d, integers, kind, max_rounds, heur, &mut LatexObserver::new())
+    } else {
+        cutting_plane_with(d, integers, kind, max_rounds, heur, &mut NoObserver)
+    }
+}
+
+/// `cutting_plane`, calling `obs` during the simplex and for every cut.
+pub fn cutting_plane_with<F: OrdField + Floor, O: Observer<F>>(d: &mut Dictionary<F>,
+                                                               integers: &mut Vec<usize>,
+                                                               kind: CutKind, max_rounds: usize,
+                                                               heur: Heuristic, obs: &mut O)
+                                                               -> Result<(SolveResult<F>, usize), Error> {
+    let mut res = d.run_simplex_with(heur, obs);
+    check_overflow(d)?;
+    let mut rounds = 0;
+    while rounds < max_rounds {
+        if res.optimum().is_none() { break; }
+        let cuts = gomory_cuts(d, integers, kind);
+        if cuts.is_empty() { break; }
+        rounds += 1;
+        for cut in cuts.iter() {
+            obs.cut(cut);
+            let labels = d.add_constraint(&cut.coeffs, OrderRel::GT, cut.rhs)?;
+            if kind == CutKind::Fractional {
+                integers.extend(labels);
+            }
+        }
+        res = d.reoptimize(heur);
+        check_overflow(d)?;
+        obs.cut_round(d);
+    }
+    Ok((res, rounds))
+}
+
+/// Fails if a coefficient of `d` is unordered, which is how the overflows of
+/// `Rational` show.
+fn check_overflow<F: OrdField>(d: &Dictionary<F>) -> Result<(), Error> {
+    let m = &d.m.m[..d.h() * d.w()];
+    if m.iter().chain(d.obj.iter()).any(|&x| is_unordered(x)) {
+        let msg = "the coefficients of the dictionary overflowed during the cutting planes";
+        return Err(Error::Numerical(msg.to_string()));
+    }
+    Ok(())
+}
+
+/// Exact copy of `d` with rational numbers. Fails if a coefficient cannot be
+/// represented.
+pub fn to_rational(d: &Dictionary<f64>) -> Option<Dictionary<Rational>> {
+    let conv = |v: &[f64]| -> Option<Vec<Rational>> {
+        v.iter().map(|&x| Rational::approximate_float(x)).collect()
+    };
+    let m = conv(&d.m.m[..d.h() * d.w()])?;
+    Some(Dictionary {
+        m: Matrix { h: d.h(), w: d.w(), m },
+        ll: d.ll.clone(),
+        lc: d.lc.clone(),
+        obj: conv(&d.obj)?,
+        weq: conv(&d.weq)?,
+        var_name: d.var_name,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn knapsack() -> (Dictionary<f64>, Vec<usize>) {
+        let lp = make_knapsack();
+        let d = lp.to_dict().unwrap();
+        let mut integers = lp.integer_labels().unwrap();
+        integers.extend(integral_slacks(&d, &integers));
+        (d, integers)
+    }
+
+    #[test]
+    fn test_integral_slacks() {
+        let (d, integers) = knapsack();
+        assert_eq!(integral_slacks(&d, &[1, 2]), vec![3, 4]);
+        assert_eq!(integers, vec![1, 2, 3, 4]);
+        assert_eq!(integral_slacks(&d, &[1]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_fractional_cut() {
+        let (mut d, integers) = knapsack();
+        d.run_simplex(Heuristic::Bland, false);
+        let cuts = gomory_cuts(&d, &integers, CutKind::Fractional);
+        assert!(!cuts.is_empty());
+        // Cuts off the optimum of the relaxation, x = 3, y = 1.5
+        let values = d.values();
+        for cut in cuts.iter() {
+            let lhs: f64 = cut.coeffs.iter().map(|&(l, a)| a * values[l]).sum();
+            assert!(lhs < cut.rhs);
+        }
+    }
+
+    #[test]
+    fn test_cutting_plane_rational() {
+        let (d, mut integers) = knapsack();
+        let mut d = to_rational(&d).unwrap();
+        let (res, rounds) = cutting_plane(&mut d, &mut integers, CutKind::Fractional, 50,
+                                          Heuristic::Bland, false).unwrap();
+        assert_eq!(res, SolveResult::Optimal(Rational::from_integer(20)));
+        assert!(rounds > 0);
+    }
+
+    #[test]
+    fn test_cutting_plane_mixed() {
+        let (mut d, mut integers) = knapsack();
+        let (res, _) = cutting_plane(&mut d, &mut integers, CutKind::MixedInteger, 50,
+                                     Heuristic::Bland, false).unwrap();
+        assert!((res.optimum().unwrap() - 20.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_overflow() {
+        // x_1 = 3 * 2^62 - 3 x_2 after the first pivot, which does not fit in 64 bits
+        let mut d = Dictionary {
+            m: Matrix { h: 1, w: 2, m: vec![Rational::from_integer(1 << 62), Rational::new(-1, 3)] },
+            ll: vec![2],
+            lc: vec![0, 1],
+            obj: vec![Rational::from_integer(0), Rational::from_integer(1)],
+            weq: vec![Rational::from_integer(0); 2],
+            var_name: "x",
+        };
+        assert!(cutting_plane(&mut d, &mut vec![1], CutKind::Fractional, 10, Heuristic::Bland, false).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Primal heuristics, which look for good integral solutions without
+//! branching, to give the branch-and-bound an early incumbent.
+//!
+//! Rounding heuristics start from the solution of the relaxation: simple
+//! rounding rounds every integer variable to the nearest integer, while
+//! feasible rounding rounds them one at a time, in a direction which keeps all
+//! the constraints satisfied. Diving heuristics repeatedly bound a fractional
+//! variable on one side and re-optimize the dictionary, trying the other side
+//! when this makes the relaxation infeasible.
+
+use std::time::{Duration, Instant};
+
+use linear_system::*;
+use branch_bound::INT_TOL;
+
+/// Largest violation of a constraint accepted by `evaluate`.
+const FEAS_TOL: f64 = 1e-7;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DiveRule {
+    Fractional, // the variable closest to an integer is rounded to it
+    Guided, // the variable closest to the incumbent is rounded towards it
+}
+
+#[derive(Clone, Copy)]
+pub struct PrimalHeuristics {
+    pub heur: Heuristic,
+    pub max_nodes: usize, // re-optimizations allowed to the dives
+    pub time_limit: Option<Duration>,
+}
+
+struct Budget {
+    nodes: usize,
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    fn consume(&mut self) -> bool {
+        if self.nodes == 0 || self.deadline.is_some_and(|t| Instant::now() >= t) {
+            return false;
+        }
+        self.nodes -= 1;
+        true
+    }
+}
+
+fn is_integral(v: f64) -> bool {
+    (v - v.round()).abs() <= INT_TOL
+}
+
+/// Objective and values of all the variables of `d`, indexed by label, when
+/// its non-basic variables take the values of `x`, or `None` if this point is
+/// not feasible.
+pub fn evaluate(d: &Dictionary<f64>, x: &[f64]) -> Option<(f64, Vec<f64>)> {
+    let len = d.ll.iter().chain(d.lc.iter()).cloned().max().unwrap_or(0) + 1;
+    let mut values = x.to_vec();
+    values.resize(len.max(x.len()), 0.0);
+    values[0] = 1.0;
+    if d.lc[1..].iter().any(|&l| values[l] < -FEAS_TOL) {
+        return None;
+    }
+    for i in 0..d.h() {
+        let v: f64 = (0..d.w()).map(|j| d.m.at(i, j) * values[d.lc[j]]).sum();
+        if v < -FEAS_TOL {
+            return None;
+        }
+        values[d.ll[i]] = v.max(0.0);
+    }
+    let z = (0..d.w()).map(|j| d.obj[j] * values[d.lc[j]]).sum();
+    Some((z, values))
+}
+
+fn integral_point(d: &Dictionary<f64>, x: &[f64], integers: &[usize]) -> Option<(f64, Vec<f64>)> {
+    evaluate(d, x).filter(|(_, values)| integers.iter().all(|&l| is_integral(values[l])))
+}
+
+/// Rounds the integer variables of `x`, the solution of a relaxation of the
+/// initial dictionary `d`, to the nearest integer.
+pub fn simple_rounding(d: &Dictionary<f64>, x: &[f64], integers: &[usize]) -> Option<(f64, Vec<f64>)> {
+    let mut x = x.to_vec();
+    for &l in integers.iter() {
+        if let Some(v) = x.get_mut(l) {
+            *v = v.round();
+        }
+    }
+    integral_point(d, &x, integers)
+}
+
+/// Rounds the integer variables of `x` one at a time, first in the direction
+/// which improves the objective of `d`, and fails if neither direction keeps
+/// the point feasible.
+pub fn feasible_rounding(d: &Dictionary<f64>, x: &[f64], integers: &[usize]) -> Option<(f64, Vec<f64>)> {
+    let mut x = x.to_vec();
+    for &l in integers.iter() {
+        let j = match d.lc.iter().position(|&c| c == l) {
+            Some(j) if l < x.len() => j,
+            _ => continue,
+        };
+        let v = x[l];
+        if is_integral(v) {
+            x[l] = v.round();
+            continue;
+        }
+        let sides = if d.obj[j] > 0.0 { [v.ceil(), v.floor()] } else { [v.floor(), v.ceil()] };
+        let mut rounded = false;
+        for &side in sides.iter() {
+            x[l] = side;
+            if evaluate(d, &x).is_some() {
+                rounded = true;
+                break;
+            }
+        }
+        if !rounded {
+            return None;
+        }
+    }
+    integral_point(d, &x, integers)
+}
+
+/// The fractional variable to bound next, with the side to try first.
+fn dive_choice(values: &[f64], integers: &[usize], rule: DiveRule,
+               guide: Option<&[f64]>) -> Option<(usize, f64, OrderRel)> {
+    let mut best: Option<(usize, f64, f64)> = None;
+    for &l in integers.iter() {
+        let v = values[l];
+        if is_integral(v) { continue; }
+        let target = match (rule, guide) {
+            (DiveRule::Guided, Some(g)) if l < g.len() => g[l],
+            _ => v.round(),
+        };
+        if best.is_none_or(|(bl, bt, _)| (v - target).abs() < (values[bl] - bt).abs()) {
+            best = Some((l, target, v));
+        }
+    }
+    best.map(|(l, target, v)| (l, v, if target < v { OrderRel::LT } else { OrderRel::GT }))
+}
+
+impl PrimalHeuristics {
+    pub fn new() -> PrimalHeuristics {
+        PrimalHeuristics {
+            heur: Heuristic::Dumb,
+            max_nodes: 200,
+            time_limit: None,
+        }
+    }
+
+    fn dive(&self, relaxed: &Dictionary<f64>, integers: &[usize], rule: DiveRule,
+            guide: Option<&[f64]>, budget: &mut Budget) -> Option<(f64, Vec<f64>)> {
+        let mut d = relaxed.clone();
+        loop {
+            let mut values = d.values();
+            let (label, v, first) = match dive_choice(&values, integers, rule, guide) {
+                Some(c) => c,
+                None => {
+                    for &l in integers.iter() {
+                        values[l] = values[l].round();
+                    }
+                    return Some((d.obj[0], values));
+                },
+            };
+            let second = if first == OrderRel::LT { OrderRel::GT } else { OrderRel::LT };
+            let mut next = None;
+            for &rel in [first, second].iter() {
+                if !budget.consume() {
+                    return None;
+                }
+                let rhs = if rel == OrderRel::LT { v.floor() } else { v.ceil() };
+                let mut child = d.clone();
+                child.add_constraint(&[(label, 1.0)], rel, rhs).ok()?;
+                if child.reoptimize(self.heur).optimum().is_some() {
+                    next = Some(child);
+                    break;
+                }
+            }
+            d = next?;
+        }
+    }
+
+    /// Runs the rounding heuristics then the dives from `relaxed`, a solved
+    /// relaxation of the initial dictionary `d`, and returns the best
+    /// integral solution found.
+    pub fn run(&self, d: &Dictionary<f64>, relaxed: &Dictionary<f64>,
+               integers: &[usize]) -> Option<(f64, Vec<f64>)> {
+        fn keep_best(best: &mut Option<(f64, Vec<f64>)>, cand: Option<(f64, Vec<f64>)>) {
+            if let Some((z, values)) = cand {
+                if best.as_ref().is_none_or(|&(b, _)| z > b) {
+                    *best = Some((z, values));
+                }
+            }
+        }
+
+        let mut budget = Budget {
+            nodes: self.max_nodes,
+            deadline: self.time_limit.map(|t| Instant::now() + t),
+        };
+        let x = relaxed.values();
+        let mut best = None;
+        keep_best(&mut best, simple_rounding(d, &x, integers));
+        keep_best(&mut best, feasible_rounding(d, &x, integers));
+        for &rule in [DiveRule::Fractional, DiveRule::Guided].iter() {
+            let guide = best.as_ref().map(|(_, values): &(f64, Vec<f64>)| values.clone());
+            let cand = self.dive(relaxed, integers, rule, guide.as_deref(), &mut budget);
+            keep_best(&mut best, cand);
+        }
+        best
+    }
+}
+
+impl Default for PrimalHeuristics {
+    fn default() -> PrimalHeuristics {
+        PrimalHeuristics::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn knapsack() -> (Dictionary<f64>, Dictionary<f64>, Vec<usize>) {
+        let lp = make_knapsack();
+        let d = lp.to_dict().unwrap();
+        let mut relaxed = d.clone();
+        relaxed.run_simplex(Heuristic::Bland, false);
+        (d, relaxed, lp.integer_labels().unwrap())
+    }
+
+    #[test]
+    fn test_rounding() {
+        let (d, _, integers) = knapsack();
+        let x = [1.0, 3.0, 1.5, 0.0, 0.0];
+        assert!(simple_rounding(&d, &x, &integers).is_none()); // x = 3, y = 2
+        let (z, values) = feasible_rounding(&d, &x, &integers).unwrap();
+        assert_eq!(z, 19.0);
+        assert_eq!(&values[1..3], &[3.0, 1.0]);
+    }
+
+    #[test]
+    fn test_dive() {
+        let (_, relaxed, integers) = knapsack();
+        let h = PrimalHeuristics::new();
+        let mut budget = Budget { nodes: 100, deadline: None };
+        let (z, values) = h.dive(&relaxed, &integers, DiveRule::Fractional, None, &mut budget).unwrap();
+        assert!(z <= 20.0 + 1e-9);
+        assert!(integers.iter().all(|&l| values[l] == values[l].round()));
+        assert!(6.0 * values[1] + 4.0 * values[2] <= 24.0 + 1e-9);
+
+        let mut budget = Budget { nodes: 0, deadline: None };
+        assert!(h.dive(&relaxed, &integers, DiveRule::Fractional, None, &mut budget).is_none());
+    }
+
+    #[test]
+    fn test_run() {
+        let (d, relaxed, integers) = knapsack();
+        let (z, _) = PrimalHeuristics::new().run(&d, &relaxed, &integers).unwrap();
+        assert!((19.0 - 1e-9..=20.0 + 1e-9).contains(&z));
+    }
+}
@@ -0,0 +1,209 @@
+//! Irreducible infeasible subsystems: a set of constraints and bounds which has
+//! no solution, but which has one as soon as any of its members is removed.
+//!
+//! The first phase of the simplex gives, through the reduced costs of the
+//! auxiliary problem, the constraints used by a proof of infeasibility (the
+//! support of a Farkas certificate), which is usually small. The deletion
+//! filter then tries to remove each of them in turn, keeping only the ones
+//! without which the subsystem becomes feasible.
+//!
+//! Variables without a lower bound are non-negative in every subsystem, as in
+//! `standard_form`. The others are free in the subsystems which leave out
+//! their lower bound, which is a member like any other. When a variable has
+//! several lower bounds, only the last one counts.
+
+use std::collections::HashMap;
+
+use linear_system::*;
+use parser::{LinearProgram, PInequation, Locations};
+use error::Error;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Constraint {
+    Row(usize), // index in `ineqs`
+    Upper(usize), // upper bound of `bounds[k]`
+    Lower(usize), // lower bound of `bounds[k]`
+}
+
+/// All the constraints and bounds of `lp`.
+pub fn constraints(lp: &LinearProgram) -> Vec<Constraint> {
+    let mut last_lower = HashMap::new();
+    for (k, b) in lp.bounds.iter().enumerate() {
+        if b.lower.is_some() {
+            last_lower.insert(&b.var[..], k);
+        }
+    }
+    let mut res: Vec<Constraint> = (0..lp.ineqs.len()).map(Constraint::Row).collect();
+    for (k, b) in lp.bounds.iter().enumerate() {
+        if b.upper.is_some() {
+            res.push(Constraint::Upper(k));
+        }
+        if last_lower.get(&b.var[..]) == Some(&k) {
+            res.push(Constraint::Lower(k));
+        }
+    }
+    res
+}
+
+/// Writes each variable of `lp` with a lower bound among `constraints(lp)` as
+/// the difference `x - x~` of two non-negative variables in `rows`, so that it
+/// is free when its lower bound is not one of them. Returns the variables of
+/// the rows, those of `lp` followed by the `x~`, and the indices in `lp.vars`
+/// of the variables which are split.
+pub fn split_free(lp: &LinearProgram, rows: &mut [PInequation<f64>]) -> (Vec<String>, Vec<usize>) {
+    let mut free: Vec<usize> = lp.bounds.iter()
+        .filter(|b| b.lower.is_some())
+        .filter_map(|b| lp.vars.iter().position(|x| *x == b.var))
+        .collect();
+    free.sort();
+    free.dedup();
+    let negative = |j: usize| format!("{}~", lp.vars[j]);
+    for row in rows.iter_mut() {
+        let mut prods = Vec::new();
+        for &(a, ref x) in row.prods.iter() {
+            prods.push((a, x.clone()));
+            if let Some(&j) = free.iter().find(|&&j| lp.vars[j] == *x) {
+                prods.push((-a, negative(j)));
+            }
+        }
+        row.prods = prods;
+    }
+    let vars = lp.vars.iter().cloned().chain(free.iter().map(|&j| negative(j))).collect();
+    (vars, free)
+}
+
+/// `c` as it is written in `lp`, preceded by its line if `loc` gives the
+/// lines of the parsed program.
+pub fn describe(lp: &LinearProgram, loc: Option<&Locations>, c: Constraint) -> String {
+    let s = match c {
+        Constraint::Row(i) => format!("constraint {}: {}", i + 1, lp.ineqs[i]),
+        Constraint::Upper(k) => format!("bound: {} <= {}", lp.bounds[k].var, lp.bounds[k].upper.unwrap()),
+        Constraint::Lower(k) => format!("bound: {} >= {}", lp.bounds[k].var, lp.bounds[k].lower.unwrap()),
+    };
+    let line = loc.map(|l| match c {
+        Constraint::Row(i) => l.ineqs[i],
+        Constraint::Upper(k) | Constraint::Lower(k) => l.bounds[k],
+    });
+    match line {
+        Some(line) => format!("line {}: {}", line, s),
+        None => s,
+    }
+}
+
+/// The rows that `c` adds to the dictionary, equalities being split in two.
+pub fn rows_of(lp: &LinearProgram, c: Constraint) -> Vec<PInequation<f64>> {
+    let bound = |k: usize, kind, cst| PInequation {
+        prods: vec![(1.0, lp.bounds[k].var.clone())],
+        kind,
+        cst,
+    };
+    match c {
+        Constraint::Row(i) if lp.ineqs[i].kind == OrderRel::EQ => {
+            let ineq = &lp.ineqs[i];
+            vec![PInequation { kind: OrderRel::LT, ..ineq.clone() },
+                 PInequation { kind: OrderRel::GT, ..ineq.clone() }]
+        },
+        Constraint::Row(i) => vec![lp.ineqs[i].clone()],
+        Constraint::Upper(k) => vec![bound(k, OrderRel::LT, lp.bounds[k].upper.unwrap())],
+        Constraint::Lower(k) => vec![bound(k, OrderRel::GT, lp.bounds[k].lower.unwrap())],
+    }
+}
+
+/// `None` if the constraints of `set` have a common solution, otherwise the
+/// ones which are used by a proof of infeasibility.
+fn certificate(lp: &LinearProgram, set: &[Constraint],
+               heur: Heuristic) -> Result<Option<Vec<Constraint>>, Error> {
+    let mut ineqs = Vec::new();
+    let mut owner = Vec::new();
+    for (k, &c) in set.iter().enumerate() {
+        for row in rows_of(lp, c) {
+            ineqs.push(row);
+            owner.push(k);
+        }
+    }
+    let (vars, _) = split_free(lp, &mut ineqs);
+    let n = vars.len();
+    let sub = LinearProgram::new(Maximize, Vec::new(), ineqs, Vec::new(), Vec::new(), vars);
+    let labels = match sub.to_dict()?.infeasibility_certificate(heur) {
+        Some(labels) => labels,
+        None => return Ok(None),
+    };
+
+    let mut used = init_zero_vec(set.len(), false);
+    for l in labels.into_iter().filter(|&l| l > n) {
+        used[owner[l - n - 1]] = true;
+    }
+    Ok(Some(set.iter().zip(used).filter(|&(_, u)| u).map(|(&c, _)| c).collect()))
+}
+
+/// An irreducible infeasible subsystem of `lp`, or `None` if it is feasible.
+pub fn iis(lp: &LinearProgram, heur: Heuristic) -> Result<Option<Vec<Constraint>>, Error> {
+    let mut set = match certificate(lp, &constraints(lp), heur)? {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+    // The first `k` members are needed: the subsystem is feasible without them
+    let mut k = 0;
+    while k < set.len() {
+        let mut rest = set.clone();
+        rest.remove(k);
+        match certificate(lp, &rest, heur)? {
+            Some(support) => set = support,
+            None => k += 1,
+        }
+    }
+    Ok(Some(set))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    #[test]
+    fn test_rows() {
+        let lp = make_infeasible();
+        assert_eq!(iis(&lp, Heuristic::Bland).unwrap(), Some(vec![Constraint::Row(1), Constraint::Row(3)]));
+        assert_eq!(describe(&lp, None, Constraint::Row(3)), "constraint 4: 1 x - 1 y >= 3");
+    }
+
+    #[test]
+    fn test_bounds() {
+        let (lp, loc) = Parser::parse_lp_with_locations("MAXIMIZE x\nSUBJECT TO\nx + y >= 5\nx <= 7\n\
+                                                         BOUNDS\n0 <= x <= 2\n0 <= y <= 2\n\
+                                                         VARIABLES\nx\ny\n").unwrap();
+        let res = iis(&lp, Heuristic::Dumb).unwrap().unwrap();
+        assert_eq!(res, vec![Constraint::Row(0), Constraint::Upper(0), Constraint::Upper(1)]);
+        assert_eq!(describe(&lp, None, res[2]), "bound: y <= 2");
+        assert_eq!(describe(&lp, Some(&loc), res[0]), "line 3: constraint 1: 1 x + 1 y >= 5");
+        assert_eq!(describe(&lp, Some(&loc), res[2]), "line 7: bound: y <= 2");
+    }
+
+    #[test]
+    fn test_equality() {
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx + y = 1\ny <= 4\nBOUNDS\n\
+                                   x >= 2\nVARIABLES\nx\ny\n").unwrap();
+        assert_eq!(iis(&lp, Heuristic::Bland).unwrap(), Some(vec![Constraint::Row(0), Constraint::Lower(0)]));
+    }
+
+    #[test]
+    fn test_feasible() {
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx + y <= 2\nBOUNDS\nVARIABLES\nx\ny\n").unwrap();
+        assert_eq!(iis(&lp, Heuristic::Bland).unwrap(), None);
+    }
+
+    #[test]
+    fn test_negative_bounds() {
+        // x and y may be negative, so the first row alone is feasible
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx + y <= -3\nx <= 1\nx + y >= 10\nBOUNDS\n\
+                                   x >= -5\ny >= -5\nVARIABLES\nx\ny\n").unwrap();
+        assert_eq!(iis(&lp, Heuristic::Bland).unwrap(), Some(vec![Constraint::Row(0), Constraint::Row(2)]));
+
+        // only the last lower bound of x counts
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx + y <= -12\nBOUNDS\nx >= 3\nx >= -5\n\
+                                   y >= -5\nVARIABLES\nx\ny\n").unwrap();
+        assert_eq!(iis(&lp, Heuristic::Bland).unwrap(),
+                   Some(vec![Constraint::Row(0), Constraint::Lower(1), Constraint::Lower(2)]));
+        assert_eq!(describe(&lp, None, Constraint::Lower(1)), "bound: x >= -5");
+    }
+}
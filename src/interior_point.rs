@@ -0,0 +1,381 @@
+//! Primal-dual interior-point method with Mehrotra's predictor-corrector.
+//!
+//! A dictionary `x_B = m_0 + M x_N` is seen as the standard form program
+//! `max c.x` subject to `x_B - M x_N = m_0` and `x >= 0`, where `c` is the
+//! objective row of the dictionary. Each iteration solves the normal equations
+//! `A D A^T dy = r` with a dense Cholesky factorization.
+
+use linear_system::*;
+
+/// Pivots of the Cholesky factorization below this (relative) value are
+/// considered to come from linearly dependent rows.
+const PIVOT_TOL: f64 = 1e-30;
+
+/// Iterates larger than this mean that the primal or the dual diverges.
+const DIVERGENCE: f64 = 1e12;
+
+/// Fraction of the step to the boundary that is actually taken.
+const STEP_FACTOR: f64 = 0.99;
+
+#[derive(Clone, Copy, Debug)]
+pub struct InteriorPoint {
+    pub tol: f64,
+    pub max_iter: usize,
+}
+
+/// Solution found by the interior-point method.
+#[derive(Clone, Debug)]
+pub struct IpmSolution {
+    pub result: SolveResult<f64>,
+    pub x: Vec<f64>, // primal value, indexed by variable label
+    pub y: Vec<f64>, // dual value of each line of the dictionary
+    pub z: Vec<f64>, // reduced cost, indexed by variable label
+    pub iterations: usize,
+}
+
+/// The standard form `A x = b, x >= 0` of a dictionary. The columns are the
+/// non-basic variables (`lc[1..]`) followed by the basic ones (`ll`), whose
+/// columns form an identity and are not stored.
+struct StandardForm {
+    m: usize,
+    n_n: usize,
+    cols: Vec<Vec<(usize, f64)>>, // non-zero entries of the first `n_n` columns
+    b: Vec<f64>,
+    c: Vec<f64>, // minimized, so this is the opposite of the objective
+    labels: Vec<usize>,
+}
+
+impl StandardForm {
+    fn new(d: &Dictionary<f64>) -> StandardForm {
+        let n_n = d.w() - 1;
+        let mut cols = Vec::with_capacity(n_n);
+        for j in 1..d.w() {
+            let col: Vec<(usize, f64)> = (0..d.h())
+                .filter(|&i| d.m.at(i, j) != 0.0)
+                .map(|i| (i, -d.m.at(i, j)))
+                .collect();
+            cols.push(col);
+        }
+        let mut c: Vec<f64> = d.obj[1..].iter().map(|&c_j| -c_j).collect();
+        c.extend(init_zero_vec(d.h(), 0.0));
+        let mut labels: Vec<usize> = d.lc[1..].to_vec();
+        labels.extend(d.ll.iter().cloned());
+
+        StandardForm {
+            m: d.h(),
+            n_n,
+            cols,
+            b: (0..d.h()).map(|i| d.m.at(i, 0)).collect(),
+            c,
+            labels,
+        }
+    }
+
+    fn n(&self) -> usize {
+        self.n_n + self.m
+    }
+
+    /// `A x`
+    fn mul(&self, x: &[f64]) -> Vec<f64> {
+        let mut res = x[self.n_n..].to_vec();
+        for (k, col) in self.cols.iter().enumerate() {
+            for &(i, a) in col.iter() {
+                res[i] += a * x[k];
+            }
+        }
+        res
+    }
+
+    /// `A^T y`
+    fn mul_t(&self, y: &[f64]) -> Vec<f64> {
+        let mut res: Vec<f64> = self.cols.iter()
+            .map(|col| col.iter().map(|&(i, a)| a * y[i]).sum())
+            .collect();
+        res.extend_from_slice(y);
+        res
+    }
+
+    /// `A D A^T` for a diagonal matrix `D`. Only the lower triangle is filled.
+    fn normal_matrix(&self, d: &[f64]) -> Matrix<f64> {
+        let mut res = Matrix::allocate_zeroed(self.m, self.m);
+        for (k, col) in self.cols.iter().enumerate() {
+            for &(i, a_i) in col.iter() {
+                for &(l, a_l) in col.iter() {
+                    if l > i { break; }
+                    let old = res.at(i, l);
+                    res.set_at(i, l, old + d[k] * a_i * a_l);
+                }
+            }
+        }
+        for i in 0..self.m {
+            let old = res.at(i, i);
+            res.set_at(i, i, old + d[self.n_n + i]);
+        }
+        res
+    }
+}
+
+/// In-place Cholesky factorization `A = L L^T` of a symmetric positive
+/// semi-definite matrix given by its lower triangle. Tiny pivots are replaced
+/// by a huge value, which amounts to ignoring the dependent rows.
+pub fn cholesky(a: &mut Matrix<f64>) {
+    let n = a.h;
+    let max_diag = (0..n).fold(0.0f64, |acc, i| acc.max(a.at(i, i).abs()));
+    for j in 0..n {
+        let mut d = a.at(j, j);
+        for k in 0..j {
+            d -= a.at(j, k) * a.at(j, k);
+        }
+        let l_jj = if d <= PIVOT_TOL * (1.0 + max_diag) { 1e64 } else { d.sqrt() };
+        a.set_at(j, j, l_jj);
+        for i in j+1..n {
+            let mut s = a.at(i, j);
+            for k in 0..j {
+                s -= a.at(i, k) * a.at(j, k);
+            }
+            a.set_at(i, j, s / l_jj);
+        }
+    }
+}
+
+/// Solves `L L^T x = r` where `l` has been factorized by `cholesky`.
+pub fn cholesky_solve(l: &Matrix<f64>, r: &[f64]) -> Vec<f64> {
+    let n = l.h;
+    let mut y = r.to_vec();
+    for i in 0..n {
+        let s: f64 = (0..i).map(|k| l.at(i, k) * y[k]).sum();
+        y[i] = (y[i] - s) / l.at(i, i);
+    }
+    for i in (0..n).rev() {
+        let s: f64 = (i+1..n).map(|k| l.at(k, i) * y[k]).sum();
+        y[i] = (y[i] - s) / l.at(i, i);
+    }
+    y
+}
+
+fn dot(u: &[f64], v: &[f64]) -> f64 {
+    u.iter().zip(v.iter()).map(|(a, b)| a * b).sum()
+}
+
+fn norm(u: &[f64]) -> f64 {
+    dot(u, u).sqrt()
+}
+
+fn norm_inf(u: &[f64]) -> f64 {
+    u.iter().fold(0.0, |acc, x| acc.max(x.abs()))
+}
+
+/// Largest step in `[0, 1]` keeping `x + alpha dx` non-negative.
+fn max_step(x: &[f64], dx: &[f64]) -> f64 {
+    let mut alpha: f64 = 1.0;
+    for (&x_i, &dx_i) in x.iter().zip(dx.iter()) {
+        if dx_i < 0.0 {
+            alpha = alpha.min(-x_i / dx_i);
+        }
+    }
+    alpha
+}
+
+impl InteriorPoint {
+    pub fn new() -> InteriorPoint {
+        InteriorPoint {
+            tol: 1e-8,
+            max_iter: 100,
+        }
+    }
+
+    /// Solves the normal equations for the Newton direction whose
+    /// complementarity right hand side is `r_xs`.
+    fn direction(sf: &StandardForm, l: &Matrix<f64>, x: &[f64], s: &[f64],
+                 rb: &[f64], rc: &[f64], r_xs: &[f64]) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let n = sf.n();
+        // dx = S^-1 r_xs + D rc + D A^T dy, with D = X S^-1
+        let mut u = init_zero_vec(n, 0.0);
+        for k in 0..n {
+            u[k] = (r_xs[k] + x[k] * rc[k]) / s[k];
+        }
+        let au = sf.mul(&u);
+        let rhs: Vec<f64> = (0..sf.m).map(|i| -rb[i] - au[i]).collect();
+        let dy = cholesky_solve(l, &rhs);
+        let atdy = sf.mul_t(&dy);
+        let mut dx = init_zero_vec(n, 0.0);
+        let mut ds = init_zero_vec(n, 0.0);
+        for k in 0..n {
+            ds[k] = -rc[k] - atdy[k];
+            dx[k] = u[k] + x[k] / s[k] * atdy[k];
+        }
+        (dx, dy, ds)
+    }
+
+    /// Mehrotra's heuristic starting point.
+    fn starting_point(sf: &StandardForm) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let n = sf.n();
+        let mut aat = sf.normal_matrix(&init_zero_vec(n, 1.0));
+        cholesky(&mut aat);
+        let mut x = sf.mul_t(&cholesky_solve(&aat, &sf.b));
+        let y = cholesky_solve(&aat, &sf.mul(&sf.c));
+        let aty = sf.mul_t(&y);
+        let mut s: Vec<f64> = (0..n).map(|k| sf.c[k] - aty[k]).collect();
+
+        let dx = (-1.5 * x.iter().cloned().fold(f64::INFINITY, f64::min)).max(0.0);
+        let ds = (-1.5 * s.iter().cloned().fold(f64::INFINITY, f64::min)).max(0.0);
+        for k in 0..n {
+            x[k] += dx;
+            s[k] += ds;
+        }
+        let xs = dot(&x, &s);
+        let sum_x: f64 = x.iter().sum();
+        let sum_s: f64 = s.iter().sum();
+        let dx = if sum_s > 0.0 { 0.5 * xs / sum_s } else { 1.0 };
+        let ds = if sum_x > 0.0 { 0.5 * xs / sum_x } else { 1.0 };
+        for k in 0..n {
+            x[k] = (x[k] + dx).max(1e-8);
+            s[k] = (s[k] + ds).max(1e-8);
+        }
+        (x, y, s)
+    }
+
+    pub fn solve(&self, d: &Dictionary<f64>) -> IpmSolution {
+        let sf = StandardForm::new(d);
+        let n = sf.n();
+        let (mut x, mut y, mut s) = Self::starting_point(&sf);
+        let norm_b = norm(&sf.b);
+        let norm_c = norm(&sf.c);
+
+        let mut result = None;
+        let mut iterations = 0;
+        while iterations < self.max_iter {
+            let ax = sf.mul(&x);
+            let aty = sf.mul_t(&y);
+            let rb: Vec<f64> = (0..sf.m).map(|i| ax[i] - sf.b[i]).collect();
+            let rc: Vec<f64> = (0..n).map(|k| aty[k] + s[k] - sf.c[k]).collect();
+            let pobj = dot(&sf.c, &x);
+            let dobj = dot(&sf.b, &y);
+            let mu = dot(&x, &s) / n as f64;
+
+            if norm(&rb) / (1.0 + norm_b) < self.tol && norm(&rc) / (1.0 + norm_c) < self.tol
+                && (pobj - dobj).abs() / (1.0 + pobj.abs()) < self.tol {
+                result = Some(SolveResult::Optimal(d.obj[0] - pobj));
+                break;
+            }
+            if norm_inf(&x) > DIVERGENCE {
+                result = Some(SolveResult::Unbounded);
+                break;
+            }
+            if norm_inf(&y) > DIVERGENCE {
+                result = Some(SolveResult::Infeasible);
+                break;
+            }
+            iterations += 1;
+
+            let dd: Vec<f64> = (0..n).map(|k| x[k] / s[k]).collect();
+            let mut l = sf.normal_matrix(&dd);
+            cholesky(&mut l);
+
+            // Predictor
+            let r_aff: Vec<f64> = (0..n).map(|k| -x[k] * s[k]).collect();
+            let (dx_aff, _, ds_aff) = Self::direction(&sf, &l, &x, &s, &rb, &rc, &r_aff);
+            let alpha_p = max_step(&x, &dx_aff);
+            let alpha_d = max_step(&s, &ds_aff);
+            let mu_aff = (0..n)
+                .map(|k| (x[k] + alpha_p * dx_aff[k]) * (s[k] + alpha_d * ds_aff[k]))
+                .sum::<f64>() / n as f64;
+            let sigma = (mu_aff / mu).powi(3);
+
+            // Corrector
+            let r_cor: Vec<f64> = (0..n)
+                .map(|k| -x[k] * s[k] - dx_aff[k] * ds_aff[k] + sigma * mu)
+                .collect();
+            let (dx, dy, ds) = Self::direction(&sf, &l, &x, &s, &rb, &rc, &r_cor);
+            let alpha_p = (STEP_FACTOR * max_step(&x, &dx)).min(1.0);
+            let alpha_d = (STEP_FACTOR * max_step(&s, &ds)).min(1.0);
+            for k in 0..n {
+                x[k] += alpha_p * dx[k];
+                s[k] += alpha_d * ds[k];
+            }
+            for i in 0..sf.m {
+                y[i] += alpha_d * dy[i];
+            }
+        }
+
+        let result = result.unwrap_or_else(|| {
+            // Out of iterations: blame the residual which did not converge
+            let ax = sf.mul(&x);
+            let rb: Vec<f64> = (0..sf.m).map(|i| ax[i] - sf.b[i]).collect();
+            if norm(&rb) / (1.0 + norm_b) >= self.tol {
+                SolveResult::Infeasible
+            } else {
+                SolveResult::Unbounded
+            }
+        });
+
+        let nlabels = sf.labels.iter().cloned().max().unwrap_or(0) + 1;
+        let mut x_lab = init_zero_vec(nlabels, 0.0);
+        let mut z_lab = init_zero_vec(nlabels, 0.0);
+        for (k, &label) in sf.labels.iter().enumerate() {
+            x_lab[label] = x[k];
+            z_lab[label] = s[k];
+        }
+        IpmSolution {
+            result,
+            x: x_lab,
+            y: y.iter().map(|&y_i| -y_i).collect(),
+            z: z_lab,
+            iterations,
+        }
+    }
+}
+
+impl Default for InteriorPoint {
+    fn default() -> InteriorPoint {
+        InteriorPoint::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cholesky() {
+        let mut a = Matrix { h: 2, w: 2, m: vec![4., 0., 2., 5.] };
+        cholesky(&mut a);
+        let x = cholesky_solve(&a, &[6., 7.]);
+        assert!((x[0] - 1.0).abs() < 1e-12);
+        assert!((x[1] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_optimal() {
+        let sol = InteriorPoint::new().solve(&make_dict());
+        let opt = sol.result.optimum().unwrap();
+        assert!((opt - 24.0).abs() < 1e-6);
+        assert!((sol.x[2] - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unbounded() {
+        let d = Dictionary {
+            m: Matrix { h: 1, w: 2, m: vec![1., 1.] },
+            ll: vec![2],
+            lc: vec![0, 1],
+            obj: vec![0., 1.],
+            weq: vec![0., 0.],
+            var_name: "x",
+        };
+        assert_eq!(InteriorPoint::new().solve(&d).result, SolveResult::Unbounded);
+    }
+
+    #[test]
+    fn test_infeasible() {
+        let d = Dictionary {
+            m: Matrix { h: 1, w: 2, m: vec![-1., -1.] },
+            ll: vec![2],
+            lc: vec![0, 1],
+            obj: vec![0., 1.],
+            weq: vec![0., 0.],
+            var_name: "x",
+        };
+        assert_eq!(InteriorPoint::new().solve(&d).result, SolveResult::Infeasible);
+    }
+}
@@ -0,0 +1,37 @@
+//! A simplex solver for linear and mixed-integer programs.
+//!
+//! Programs are either parsed from the LP format (`parser`) or built with the
+//! `model` API, then solved through their dictionary (`linear_system`).
+
+extern crate num;
+#[macro_use]
+extern crate log;
+
+#[allow(dead_code)]
+#[allow(unused_must_use)]
+pub mod linear_system;
+pub mod parser;
+#[allow(dead_code)]
+pub mod interior_point;
+pub mod crossover;
+pub mod basis;
+#[allow(dead_code)]
+pub mod reoptimize;
+pub mod branch_bound;
+pub mod gomory;
+pub mod rational;
+pub mod heuristics;
+pub mod presolve;
+pub mod scaling;
+pub mod iis;
+pub mod relax;
+pub mod dual;
+pub mod standard_form;
+pub mod model;
+pub mod expr;
+pub mod observer;
+pub mod validate;
+pub mod stats;
+pub mod output;
+pub mod logger;
+pub mod error;
@@ -1,10 +1,14 @@
 use std::vec::Vec;
-use num::Num;
-use std::fmt::{Display, Formatter, Error, Debug};
+use num::{Num, Integer};
+use num::rational::Ratio;
+use std::fmt::{self, Display, Formatter, Debug};
 use std::cmp::{Ordering};
 use std::mem;
+use std::time::Instant;
 
 pub use self::ObjectiveKind::*;
+use observer::*;
+use error::Error;
 
 // Utils
 pub fn init_zero_vec<T: Copy>(n: usize, val: T) -> Vec<T> {
@@ -80,8 +84,30 @@ impl<F: Num + Copy + PartialEq> Matrix<F> {
     }
 }
 
-pub trait OrdField: Num + PartialEq + Copy + PartialOrd + Display + Debug {}
-impl<F: Num + PartialEq + Copy + PartialOrd + Display + Debug> OrdField for F {}
+/// Magnitude under which a number is considered nil by the solvers: some
+/// rounding error for floats, nothing for exact arithmetic.
+pub trait Tolerance {
+    fn tolerance() -> Self;
+}
+
+impl Tolerance for f64 {
+    fn tolerance() -> f64 { 1e-9 }
+}
+
+impl Tolerance for f32 {
+    fn tolerance() -> f32 { 1e-5 }
+}
+
+impl<T: Clone + Integer> Tolerance for Ratio<T> {
+    fn tolerance() -> Ratio<T> { Ratio::from_integer(T::zero()) }
+}
+
+pub trait OrdField: Num + PartialEq + Copy + PartialOrd + Display + Debug + Tolerance {}
+impl<F: Num + PartialEq + Copy + PartialOrd + Display + Debug + Tolerance> OrdField for F {}
+
+pub fn abs<F: OrdField>(x: F) -> F {
+    if x < F::zero() { F::zero() - x } else { x }
+}
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Dictionary<F: OrdField> {
@@ -93,7 +119,7 @@ pub struct Dictionary<F: OrdField> {
     pub var_name: &'static str,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum Heuristic {
     Bland,
     Dumb,
@@ -119,6 +145,25 @@ impl<F: PartialOrd + Copy> PartialOrd for LeavingCase<F> {
     }
 }
 
+/// Outcome of a solver run. The optimum is the one of the maximized objective
+/// of the dictionary.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SolveResult<F> {
+    Optimal(F),
+    Infeasible,
+    Unbounded,
+}
+
+impl<F: Copy> SolveResult<F> {
+    pub fn optimum(&self) -> Option<F> {
+        match *self {
+            SolveResult::Optimal(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Step {
     Finished,
     Unbounded(usize), // "entering variable"
@@ -126,12 +171,20 @@ pub enum Step {
 }
 
 impl<F: OrdField> Dictionary<F> {
-    pub fn check_integrity(&self) {
-        //println!("{:?}", self);
-        assert!(self.m.h == self.ll.len());
-        assert!(self.m.w == self.lc.len());
-        assert!(self.m.w == self.obj.len());
-        assert!(self.weq.len() == self.m.w);
+    pub fn check_integrity(&self) -> Result<(), Error> {
+        if self.m.h != self.ll.len() {
+            return Err(Error::Model(format!("the dictionary has {} lines but {} basic variables",
+                                            self.m.h, self.ll.len())));
+        }
+        let columns = [("non-basic variables", self.lc.len()), ("objective coefficients", self.obj.len()),
+                       ("equality weights", self.weq.len())];
+        for &(what, len) in columns.iter() {
+            if len != self.m.w {
+                return Err(Error::Model(format!("the dictionary has {} columns but {} {}",
+                                                self.m.w, len, what)));
+            }
+        }
+        Ok(())
     }
 
     pub fn w(&self) -> usize {
@@ -143,6 +196,29 @@ impl<F: OrdField> Dictionary<F> {
     }
 
 
+    /// Value of each variable in the basic solution, indexed by label.
+    /// The constant `x_0` is one.
+    pub fn values(&self) -> Vec<F> {
+        let n = self.ll.iter().chain(self.lc.iter()).cloned().max().unwrap_or(0) + 1;
+        let mut res = init_zero_vec(n, F::zero());
+        res[0] = F::one();
+        for (i, &label) in self.ll.iter().enumerate() {
+            res[label] = self.m.at(i, 0);
+        }
+        res
+    }
+
+    /// Coefficient of each variable in the objective, indexed by label: zero
+    /// for the basic ones.
+    pub fn reduced_costs(&self) -> Vec<F> {
+        let n = self.ll.iter().chain(self.lc.iter()).cloned().max().unwrap_or(0) + 1;
+        let mut res = init_zero_vec(n, F::zero());
+        for (j, &label) in self.lc.iter().enumerate().skip(1) {
+            res[label] = self.obj[j];
+        }
+        res
+    }
+
     fn create_first_dict(&self) -> Dictionary<F> {
        let mut m = Matrix::allocate_zeroed(self.h(), self.w() + 1);
        self.m.blit(&mut m, Rect {i: 0, j: 0, h: self.h(), w: self.w()}, 0, 0);
@@ -161,50 +237,62 @@ impl<F: OrdField> Dictionary<F> {
            weq: init_zero_vec(self.w()+1, F::zero()),
            var_name: self.var_name,
        };
-       res.check_integrity();
+       debug_assert!(res.check_integrity().is_ok());
        res
     }
 
-    /// Creates a new `Dictionary` without the component `xr`
-    /// It creates a copy of itself if xr is not a variable of `self`
+    /// Writes into `orig` the dictionary `self` without the component `xr`,
+    /// and with the objective of `orig` expressed in the new basis.
+    /// If `xr` is basic, it must be nil so that it leaves with a degenerate pivot.
     fn project_dict(&self, xr: usize, orig: &mut Dictionary<F>) {
-        if let Some(ir) = self.ll.iter().position(|&x| x == xr) {
-            self.m.blit(&mut orig.m, Rect{i: 0, j: 0, h: ir, w: self.w()}, 0, 0);
-            self.m.blit(&mut orig.m, Rect{i: ir+1, j: 0, h: self.h()-ir-1, w: self.w()}, ir, 0);
-            orig.fix_obj_after_first_phase(self, FIRST_PHASE_IDX);
-            let mut ll = self.ll.clone();
-            ll.remove(ir); // TODO(leo): optimize?
-            orig.ll= ll;
-            orig.lc = self.lc.clone();
-        } else if let Some(jr) = self.lc.iter().position(|&x| x == xr) {
-            self.m.blit(&mut orig.m, Rect{i: 0, j: 0, h: self.h(), w: jr}, 0, 0);
-            self.m.blit(&mut orig.m, Rect{i: 0, j: jr+1, h: self.h(), w: self.w()-jr-1}, 0, jr);
-            orig.fix_obj_after_first_phase(self, FIRST_PHASE_IDX);
-            let mut obj = self.obj.clone();
-            let mut lc = self.lc.clone();
-            obj.remove(jr);
-            lc.remove(jr);
-            orig.ll= self.ll.clone();
-            orig.lc = lc;
-        }
-    }
-
-    fn fix_obj_after_first_phase(&mut self, afp: &Dictionary<F>, exc: usize) {
-        let mut res = init_zero_vec(self.w(), F::zero());
-        res[0] = self.obj[0];
-        for (j, &c_j) in self.obj.iter().enumerate() {
-            let x_j = self.lc[j];
-            if x_j == exc { continue; }
-            if c_j != F::zero() {
-                if let Some(&x_i) = afp.ll.iter().find(|&x| *x == x_j) { // TODO(leo): WTF???
-                    // x_i is a primary variable of afp.
-                    for j in 0..res.len() {
-                        res[j] = res[j] + c_j * self.m.at(x_i, j); // I FIXME(leo): i hate myself
-                    }
+        let mut afp = self.clone();
+        let mut drop_line = None;
+        if let Some(ir) = afp.ll.iter().position(|&x| x == xr) {
+            let mut jmax = None;
+            for j in 1..afp.w() {
+                let a = abs(afp.m.at(ir, j));
+                if a != F::zero() && jmax.is_none_or(|(_, max)| a > max) {
+                    jmax = Some((j, a));
+                }
+            }
+            match jmax {
+                Some((je, _)) => afp.perform_pivot(je, ir),
+                None => drop_line = Some(ir), // The line is x_r = 0, it carries no constraint
+            }
+        }
+        let jr = afp.lc.iter().position(|&x| x == xr);
+
+        let lines: Vec<usize> = (0..afp.h()).filter(|&i| Some(i) != drop_line).collect();
+        let cols: Vec<usize> = (0..afp.w()).filter(|&j| Some(j) != jr).collect();
+        let mut m = Matrix::allocate_zeroed(lines.len(), cols.len());
+        for (i_new, &i) in lines.iter().enumerate() {
+            for (j_new, &j) in cols.iter().enumerate() {
+                m.set_at(i_new, j_new, afp.m.at(i, j));
+            }
+        }
+        let ll: Vec<usize> = lines.iter().map(|&i| afp.ll[i]).collect();
+        let lc: Vec<usize> = cols.iter().map(|&j| afp.lc[j]).collect();
+
+        let mut obj = init_zero_vec(lc.len(), F::zero());
+        obj[0] = orig.obj[0];
+        for (j, &c_j) in orig.obj.iter().enumerate().skip(1) {
+            if c_j == F::zero() { continue; }
+            let x_j = orig.lc[j];
+            if let Some(i) = ll.iter().position(|&x| x == x_j) {
+                for (k, o) in obj.iter_mut().enumerate() {
+                    *o = *o + c_j * m.at(i, k);
                 }
+            } else if let Some(k) = lc.iter().position(|&x| x == x_j) {
+                obj[k] = obj[k] + c_j;
             }
         }
-        self.obj = res;
+
+        orig.weq = init_zero_vec(m.w, F::zero());
+        orig.m = m;
+        orig.ll = ll;
+        orig.lc = lc;
+        orig.obj = obj;
+        debug_assert!(orig.check_integrity().is_ok());
     }
 
     fn find_first_pivot(&self) -> usize {
@@ -220,10 +308,10 @@ impl<F: OrdField> Dictionary<F> {
         res
     }
 
-    pub fn find_leaving_variable(&self, je: usize) -> LeavingCase<F> { // TODO(leo): get rid of dumb LeavingCase :/
+    fn find_leaving_variable(&self, je: usize) -> LeavingCase<F> { // TODO(leo): get rid of dumb LeavingCase :/
         use self::LeavingCase::*;
         //assert!(je != 0);
-        let coeffs = (0..self.h()).map(|i| if self.m.at(i, je) < F::zero() {
+        let coeffs = (0..self.h()).map(|i| if self.m.at(i, je) < F::zero() - F::tolerance() {
             Pos(i, F::zero() - self.m.at(i, 0) / self.m.at(i, je))
         } else {
             NonNeg
@@ -252,7 +340,7 @@ impl<F: OrdField> Dictionary<F> {
     pub fn is_solution(&self, sol: Vec<F>) -> bool {
         //assert!(sol.len() == self.obj.len() - 1);
         for i in 0..self.h() {
-            if self.eval_line(&sol, i) < F::zero() {
+            if self.eval_line(&sol, i) < F::zero() - F::tolerance() {
                 return false;
             }
         }
@@ -262,7 +350,7 @@ impl<F: OrdField> Dictionary<F> {
     pub fn find_entering_variable(&self) -> Step { //TODO(leo): handle all cases
         use self::Step::*;
         for j in 1..self.w() {
-            if self.obj[j] > F::zero() {
+            if self.obj[j] > F::tolerance() {
                 if let LeavingCase::Pos(i, _) = self.find_leaving_variable(j) {
                     return Continue(i, j)
                 } else {
@@ -275,6 +363,7 @@ impl<F: OrdField> Dictionary<F> {
 
     pub fn find_entering_variable_dumb(&self) -> Step {
         use self::Step::*;
+        if self.w() == 1 { return Finished; } // everything was presolved away
         let mut max = self.obj[1];
         let mut jmax = 1;
         for (j, &c_j) in self.obj[1..].iter().enumerate() {
@@ -283,7 +372,7 @@ impl<F: OrdField> Dictionary<F> {
 
         let j = jmax + 1;
 
-        if max > F::zero() {
+        if max > F::tolerance() {
             if let LeavingCase::Pos(i, _) = self.find_leaving_variable(j) {
                 return Continue(i, j)
             } else {
@@ -293,69 +382,43 @@ impl<F: OrdField> Dictionary<F> {
         Finished
     }
 
-    pub fn run_simplex(&mut self, heur: Heuristic, latex: bool) -> Option<F> {
-        let do_first_phase = {
-            let nil_sol: Vec<F> = init_zero_vec(self.w()-1, F::zero());
-            !self.is_solution(nil_sol)
-        };
-
-        if latex {
-            println!("Should we do the first phase? {}\n", do_first_phase);
-        }
-
-        if do_first_phase {
-            let mut d = self.create_first_dict();
-            let i = d.find_first_pivot();
-            d.perform_pivot(self.w(), i);
-            d.run_simplex(heur, latex);
-            let res = F::zero() - d.obj[0];
-            if latex {
-                println!("The minimum value of the dummy variable is {}\n", res);
-                if res > F::zero() {
-                    println!("It seems the input dictionary is not feasible!\n");
-                    return None;
-                } else {
-                    println!("Therefore it is feasible!");
-                }
-            }
-
-            if !latex && res > F::zero() {
-                println!("The simplex is not feasible!");
-                return None;
-            }
-
-
-            //println!("END OF FIRST PHASE with res {}", d.obj[0]);
-            d.project_dict(FIRST_PHASE_IDX, self);
-            //println!("DICT FOR BEGINNING OF SECOND PHASE:\n {}", self);
+    /// Runs the first phase only. Returns `None` if the dictionary is feasible,
+    /// otherwise the labels of the non-basic variables with a negative reduced
+    /// cost in the auxiliary problem: the lines of these variables alone are
+    /// enough to prove the infeasibility (Farkas' lemma).
+    pub fn infeasibility_certificate(&self, heur: Heuristic) -> Option<Vec<usize>> {
+        let nil_sol: Vec<F> = init_zero_vec(self.w()-1, F::zero());
+        if self.is_solution(nil_sol) {
+            return None;
         }
-
-        let fev: fn (&Self) -> Step = match heur {
-            Heuristic::Bland => Self::find_entering_variable,
-            Heuristic::Dumb => Self::find_entering_variable_dumb,
-        };
-
-        loop {
-            if latex {
-                println!("{}\n", self)
-            }
-            match fev(self) {
-                Step::Continue(i, j) => {
-                    if latex {
-                        println!("Performing pivot: entering {}, leaving {}\n", self.lc[j], self.ll[i]);
-                    }
-                    self.perform_pivot(j, i)
-                },
-                Step::Unbounded(_) => { // TODO: give infinite line
-                    println!("This LP is unbounded!");
-                    return None;
-                },
-                Step::Finished => break,
-            }
+        let mut d = self.create_first_dict();
+        let i = d.find_first_pivot();
+        d.perform_pivot(self.w(), i);
+        d.run_simplex(heur, false);
+        if F::zero() - d.obj[0] <= F::tolerance() {
+            return None;
         }
+        Some((1..d.w())
+             .filter(|&j| d.lc[j] != FIRST_PHASE_IDX && d.obj[j] < F::zero() - F::tolerance())
+             .map(|j| d.lc[j])
+             .collect())
+    }
 
-        return Some(self.obj[0]);
+    pub fn run_simplex(&mut self, heur: Heuristic, latex: bool) -> SolveResult<F> {
+        if latex {
+            self.run_simplex_with(heur, &mut LatexObserver::new())
+        } else {
+            self.run_simplex_with(heur, &mut NoObserver)
+        }
+    }
 
+    /// Runs the simplex, calling `obs` at each phase and pivot.
+    pub fn run_simplex_with<O: Observer<F>>(&mut self, heur: Heuristic, obs: &mut O) -> SolveResult<F> {
+        let mut simplex = Simplex::new(self.clone(), heur);
+        while simplex.step_with(obs).is_some() {}
+        let res = simplex.result().unwrap();
+        *self = simplex.into_dictionary();
+        res
     }
 
     /// `je`: entering variable
@@ -409,9 +472,223 @@ impl<F: OrdField> Dictionary<F> {
     }
 }
 
+/// The simplex run one pivot at a time, so that the caller can look at the
+/// dictionary between the pivots, choose them itself or stop early.
+pub struct Simplex<F: OrdField> {
+    d: Dictionary<F>, // the auxiliary dictionary during the first phase
+    orig: Option<Dictionary<F>>, // the dictionary of the program during the first phase
+    first_pivot: Option<(usize, usize)>, // brings the auxiliary variable into the basis
+    heur: Heuristic,
+    phase: Phase,
+    started: bool,
+    result: Option<SolveResult<F>>,
+    pivots: usize, // in the current phase
+    phase_start: Instant,
+}
+
+impl<F: OrdField> Simplex<F> {
+    pub fn new(d: Dictionary<F>, heur: Heuristic) -> Simplex<F> {
+        let nil_sol: Vec<F> = init_zero_vec(d.w()-1, F::zero());
+        let (d, orig, first_pivot, phase) = if d.is_solution(nil_sol) {
+            (d, None, None, Phase::Second)
+        } else {
+            let aux = d.create_first_dict();
+            let pivot = (aux.find_first_pivot(), d.w());
+            (aux, Some(d), Some(pivot), Phase::First)
+        };
+        Simplex { d, orig, first_pivot, heur, phase, started: false, result: None, pivots: 0,
+                  phase_start: Instant::now() }
+    }
+
+    /// The current dictionary, which is the auxiliary one during the first
+    /// phase. It is only feasible after the first pivot of this phase, which
+    /// brings the auxiliary variable into the basis.
+    pub fn dictionary(&self) -> &Dictionary<F> {
+        &self.d
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// `None` until the simplex is finished.
+    pub fn result(&self) -> Option<SolveResult<F>> {
+        self.result
+    }
+
+    /// The pivot chosen by the rule for the current dictionary.
+    pub fn next_pivot(&self) -> Step {
+        if let Some((i, j)) = self.first_pivot {
+            return Step::Continue(i, j);
+        }
+        match self.heur {
+            Heuristic::Bland => self.d.find_entering_variable(),
+            Heuristic::Dumb => self.d.find_entering_variable_dumb(),
+        }
+    }
+
+    /// Performs the next pivot, and returns it as `Continue(line, column)`,
+    /// or `Unbounded(column)` if the program is unbounded. Returns `None`
+    /// once the simplex is finished.
+    pub fn step(&mut self) -> Option<Step> {
+        self.step_with(&mut NoObserver)
+    }
+
+    pub fn step_with<O: Observer<F>>(&mut self, obs: &mut O) -> Option<Step> {
+        if self.result.is_some() {
+            return None;
+        }
+        self.start(obs);
+        match self.next_pivot() {
+            Step::Continue(i, j) => {
+                self.perform(i, j, obs);
+                Some(Step::Continue(i, j))
+            },
+            Step::Unbounded(j) => {
+                self.finish(SolveResult::Unbounded, obs);
+                Some(Step::Unbounded(j))
+            },
+            Step::Finished if self.phase == Phase::First => {
+                self.end_phase(obs);
+                let mut orig = self.orig.take().unwrap();
+                let res = F::zero() - self.d.obj[0];
+                if res > F::zero() && res <= F::tolerance() {
+                    debug!("The auxiliary variable is {}, taken as 0 by the tolerance", res);
+                }
+                if res > F::tolerance() {
+                    info!("The program is infeasible");
+                    self.d = orig;
+                    self.result = Some(SolveResult::Infeasible);
+                    obs.finish(&SolveResult::Infeasible);
+                    return None;
+                }
+                self.d.project_dict(FIRST_PHASE_IDX, &mut orig);
+                self.d = orig;
+                self.phase = Phase::Second;
+                self.started = false;
+                self.start(obs);
+                self.step_with(obs)
+            },
+            Step::Finished => {
+                let res = SolveResult::Optimal(self.d.obj[0]);
+                self.finish(res, obs);
+                None
+            },
+        }
+    }
+
+    /// Performs the pivot on line `i` and column `j` instead of the one of
+    /// the rule. It must keep the dictionary feasible: the coefficient must
+    /// be negative and the line must have the smallest ratio. The first pivot
+    /// of the first phase, which makes the auxiliary dictionary feasible, can
+    /// only be the one given by `next_pivot`. The simplex is left untouched
+    /// if the pivot is refused.
+    pub fn pivot(&mut self, i: usize, j: usize) -> Result<(), Error> {
+        self.pivot_with(i, j, &mut NoObserver)
+    }
+
+    pub fn pivot_with<O: Observer<F>>(&mut self, i: usize, j: usize, obs: &mut O) -> Result<(), Error> {
+        if self.result.is_some() {
+            return Err(Error::Usage("the simplex is finished".to_string()));
+        }
+        if let Some(first) = self.first_pivot {
+            if first != (i, j) {
+                let msg = format!("the auxiliary variable must first enter the basis at line {} and column {}",
+                                  first.0, first.1);
+                return Err(Error::Usage(msg));
+            }
+            self.start(obs);
+            self.perform(i, j, obs);
+            return Ok(());
+        }
+        if i >= self.d.h() || j == 0 || j >= self.d.w() {
+            return Err(Error::Usage(format!("there is no pivot at line {} and column {}", i, j)));
+        }
+        let a = self.d.m.at(i, j);
+        if a >= F::zero() - F::tolerance() {
+            return Err(Error::Usage(format!("the coefficient {} of the pivot is not negative", a)));
+        }
+        if let LeavingCase::Pos(_, min) = self.d.find_leaving_variable(j) {
+            if F::zero() - self.d.m.at(i, 0) / a > min + F::tolerance() {
+                return Err(Error::Usage(format!("the line {} does not have the smallest ratio", i)));
+            }
+        }
+        self.start(obs);
+        self.perform(i, j, obs);
+        Ok(())
+    }
+
+    /// The dictionary of the program: the current one, or the initial one if
+    /// the first phase is not finished.
+    pub fn into_dictionary(self) -> Dictionary<F> {
+        self.orig.unwrap_or(self.d)
+    }
+
+    fn start<O: Observer<F>>(&mut self, obs: &mut O) {
+        if !self.started {
+            self.started = true;
+            self.pivots = 0;
+            self.phase_start = Instant::now();
+            info!("{:?} phase: {} lines, {} columns", self.phase, self.d.h(), self.d.w());
+            obs.phase_start(self.phase, &self.d);
+        }
+    }
+
+    fn perform<O: Observer<F>>(&mut self, i: usize, j: usize, obs: &mut O) {
+        let (entering, leaving) = (self.d.lc[j], self.d.ll[i]);
+        let (element, before) = (self.d.m.at(i, j), self.d.obj[0]);
+        self.d.perform_pivot(j, i);
+        self.first_pivot = None;
+        let p = Pivot {
+            phase: self.phase,
+            entering,
+            leaving,
+            element,
+            objective: self.d.obj[0],
+            degenerate: abs(self.d.obj[0] - before) <= F::tolerance(),
+        };
+        self.pivots += 1;
+        debug!("Pivot {}: x_{} enters, x_{} leaves, pivot {}, objective {}",
+               self.pivots, entering, leaving, element, p.objective);
+        if p.degenerate {
+            if p.objective != before {
+                debug!("Objective change {} taken as 0 by the tolerance", p.objective - before);
+            }
+            debug!("Degenerate pivot");
+            obs.degenerate(&p);
+        }
+        obs.pivot(&p, &self.d);
+    }
+
+    fn end_phase<O: Observer<F>>(&mut self, obs: &mut O) {
+        info!("{:?} phase finished after {} pivots in {:.3?}", self.phase, self.pivots,
+              self.phase_start.elapsed());
+        obs.phase_end(self.phase, &self.d);
+    }
+
+    fn finish<O: Observer<F>>(&mut self, res: SolveResult<F>, obs: &mut O) {
+        self.end_phase(obs);
+        match res {
+            SolveResult::Optimal(z) => info!("Optimal, with the objective {}", z),
+            _ => info!("The program is unbounded"),
+        }
+        self.result = Some(res);
+        obs.finish(&res);
+    }
+}
+
+/// Each pivot, with a copy of the dictionary after it.
+impl<F: OrdField> Iterator for Simplex<F> {
+    type Item = (Step, Dictionary<F>);
+
+    fn next(&mut self) -> Option<(Step, Dictionary<F>)> {
+        self.step().map(|step| (step, self.d.clone()))
+    }
+}
+
 impl<F: OrdField> Display for Dictionary<F> {
     // TODO(leo): Print x_0 as a cte
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         write!(f, r"$$ \begin{{array}}{{ccccccccccccccc}}");
         for i in 0..self.h() {
             let _ = write!(f, "&{}_{{ {} }} &= ", self.var_name, self.ll[i]);
@@ -448,16 +725,27 @@ pub enum OrderRel {
     EQ,
 }
 
+impl Display for OrderRel {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            OrderRel::LT => write!(f, "<="),
+            OrderRel::GT => write!(f, ">="),
+            OrderRel::EQ => write!(f, "="),
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub enum ObjectiveKind {
     Maximize,
     Minimize,
 }
 
+#[derive(PartialEq, Debug, Clone)]
 pub struct Inequation<F: OrdField> {
-    coeffs: Vec<F>,
-    order: OrderRel,
-    cst: F,
+    pub coeffs: Vec<F>,
+    pub order: OrderRel,
+    pub cst: F,
 }
 
 impl<F: OrdField> Inequation<F> {
@@ -466,18 +754,132 @@ impl<F: OrdField> Inequation<F> {
     }
 }
 
+/// A program on dense rows over non-negative variables, between the parsed
+/// `LinearProgram` and the dictionary. It can also be built directly.
+#[derive(PartialEq, Debug, Clone)]
 pub struct NormalLinearProgram<F: OrdField> {
-    ineqs: Vec<Inequation<F>>,
-    obj: Vec<F>,
-    obj_kind: ObjectiveKind,
-    // TODO(leo): names
+    pub ineqs: Vec<Inequation<F>>,
+    pub obj: Vec<F>,
+    pub obj_cst: F,
+    pub obj_kind: ObjectiveKind,
+    pub names: Vec<String>,
 }
 
 impl<F: OrdField> NormalLinearProgram<F> {
-    pub fn check_integrity(&self) {
-        for v in self.ineqs.iter() {
-            assert_eq!(v.size(), self.obj.len());
+    /// A program without constraints on the variables `names`, whose
+    /// objective is `obj`.
+    pub fn new(obj_kind: ObjectiveKind, obj: Vec<F>, names: Vec<String>) -> NormalLinearProgram<F> {
+        NormalLinearProgram {
+            ineqs: Vec::new(),
+            obj,
+            obj_cst: F::zero(),
+            obj_kind,
+            names,
+        }
+    }
+
+    pub fn add_row(&mut self, coeffs: Vec<F>, order: OrderRel, cst: F) {
+        self.ineqs.push(Inequation { coeffs, order, cst });
+    }
+
+    pub fn check_integrity(&self) -> Result<(), Error> {
+        if self.names.len() != self.obj.len() {
+            return Err(Error::Model(format!("{} variables but {} objective coefficients",
+                                            self.names.len(), self.obj.len())));
+        }
+        for (i, x) in self.names.iter().enumerate() {
+            if self.names[..i].contains(x) {
+                return Err(Error::Model(format!("variable {} is defined twice", x)));
+            }
+        }
+        for (i, ineq) in self.ineqs.iter().enumerate() {
+            if ineq.size() != self.obj.len() {
+                return Err(Error::Model(format!("row {} has {} coefficients instead of {}",
+                                                i + 1, ineq.size(), self.obj.len())));
+            }
+        }
+        Ok(())
+    }
+
+    /// The dictionary of the program, maximized. The `>=` rows are negated and
+    /// the equalities split into a `<=` and a negated `>=` row; the slack of
+    /// the `i`-th line is labelled `n + i + 1`.
+    pub fn to_dict(&self) -> Dictionary<F> {
+        let n = self.obj.len();
+        let (one, minus_one) = (F::one(), F::zero() - F::one());
+        let mut rows: Vec<(F, &Inequation<F>)> = Vec::new();
+        for ineq in self.ineqs.iter() {
+            match ineq.order {
+                OrderRel::LT => rows.push((one, ineq)),
+                OrderRel::GT => rows.push((minus_one, ineq)),
+                OrderRel::EQ => {
+                    rows.push((one, ineq));
+                    rows.push((minus_one, ineq));
+                },
+            }
+        }
+
+        let mut m: Matrix<F> = Matrix::allocate_zeroed(rows.len(), n + 1);
+        for (i, &(mult, ineq)) in rows.iter().enumerate() {
+            m.set_at(i, 0, mult * ineq.cst);
+            for (j, &a) in ineq.coeffs.iter().enumerate() {
+                m.set_at(i, j + 1, F::zero() - mult * a);
+            }
+        }
+
+        let mult = match self.obj_kind {
+            Maximize => one,
+            Minimize => minus_one,
+        };
+        let mut obj = vec![mult * self.obj_cst];
+        obj.extend(self.obj.iter().map(|&c| mult * c));
+
+        Dictionary {
+            ll: (n + 1..n + 1 + m.h).collect(),
+            lc: (0..n + 1).collect(),
+            obj,
+            weq: init_zero_vec(n + 1, F::zero()),
+            m,
+            var_name: "x",
+        }
+    }
+}
+
+/// Writes `sum c_j x_j`, skipping the nil coefficients.
+fn write_sum<F: OrdField>(f: &mut Formatter, coeffs: &[F], names: &[String]) -> Result<(), fmt::Error> {
+    let mut first = true;
+    for (&c, x) in coeffs.iter().zip(names.iter()) {
+        if c == F::zero() { continue; }
+        if first {
+            write!(f, "{} {}", c, x)?;
+        } else if c < F::zero() {
+            write!(f, " - {} {}", F::zero() - c, x)?;
+        } else {
+            write!(f, " + {} {}", c, x)?;
         }
+        first = false;
+    }
+    Ok(())
+}
+
+/// Writes the program in the LP format of the parser.
+impl<F: OrdField> Display for NormalLinearProgram<F> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.obj_kind {
+            Maximize => writeln!(f, "MAXIMIZE")?,
+            Minimize => writeln!(f, "MINIMIZE")?,
+        }
+        write_sum(f, &self.obj, &self.names)?;
+        writeln!(f, "\n\nSUBJECT TO\n")?;
+        for ineq in self.ineqs.iter() {
+            write_sum(f, &ineq.coeffs, &self.names)?;
+            writeln!(f, " {} {}", ineq.order, ineq.cst)?;
+        }
+        writeln!(f, "\nBOUNDS\n\nVARIABLES\n")?;
+        for x in self.names.iter() {
+            writeln!(f, "{}", x)?;
+        }
+        Ok(())
     }
 }
 
@@ -496,9 +898,26 @@ pub fn make_dict() -> Dictionary<f64> {
     }
 }
 
+/// `max 5 x + 4 y` subject to `6 x + 4 y <= 24` and `x + 2 y <= 6` with `x`
+/// and `y` integers. The relaxation has its optimum 21 at x = 3, y = 1.5, the
+/// integer one is 20.
+#[cfg(test)]
+pub fn make_knapsack() -> ::parser::LinearProgram {
+    ::parser::Parser::parse_lp("MAXIMIZE 5 x + 4 y\nSUBJECT TO\n6 x + 4 y <= 24\n\
+                                x + 2 y <= 6\nBOUNDS\nGENERAL\nx y\nVARIABLES\nx\ny\n").unwrap()
+}
+
+/// An infeasible program, whose second and fourth rows contradict each other.
+#[cfg(test)]
+pub fn make_infeasible() -> ::parser::LinearProgram {
+    ::parser::Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\ny <= 10\nx + y <= 2\nx + 2 y <= 20\n\
+                                x - y >= 3\nBOUNDS\nVARIABLES\nx\ny\n").unwrap()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use observer::Phase;
 
     #[test]
     fn test_at() {
@@ -520,8 +939,116 @@ mod test {
         assert_eq!(lp.find_leaving_variable(1), super::LeavingCase::Pos(1, 4.0)) // TODO(leo): correct??
     }
 
+    #[test]
+    fn test_first_phase() {
+        // max x_1 st x_1 <= 8 and x_1 >= 1
+        let mut d = Dictionary {
+            m: Matrix { h: 2, w: 2, m: vec![8., -1., -1., 1.] },
+            ll: vec![2, 3],
+            lc: vec![0, 1],
+            obj: vec![0., 1.],
+            weq: vec![0., 0.],
+            var_name: "x",
+        };
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(8.));
+        d.check_integrity().unwrap();
+        assert!(!d.lc.contains(&FIRST_PHASE_IDX) && !d.ll.contains(&FIRST_PHASE_IDX));
+    }
+
     #[test]
     fn testcase_is_integre() {
-        make_dict().check_integrity();
+        make_dict().check_integrity().unwrap();
+    }
+
+    #[test]
+    fn test_normal_program() {
+        let names = vec!["x1".to_string(), "x2".to_string()];
+        let mut lp = NormalLinearProgram::new(Maximize, vec![3., 8.], names);
+        lp.add_row(vec![-1., -2.], OrderRel::LT, 8.);
+        lp.add_row(vec![3., 4.], OrderRel::LT, 12.);
+        assert!(lp.check_integrity().is_ok());
+        assert_eq!(lp.to_dict(), make_dict());
+
+        lp.add_row(vec![1.], OrderRel::GT, 0.);
+        assert!(lp.check_integrity().is_err());
+    }
+
+    #[test]
+    fn test_normal_equality() {
+        // min x + y st x + y >= 2, x - 2 y = 0
+        let mut lp: NormalLinearProgram<f64> = NormalLinearProgram::new(Minimize, vec![1., 1.], vec!["x".to_string(), "y".to_string()]);
+        lp.add_row(vec![1., 1.], OrderRel::GT, 2.);
+        lp.add_row(vec![1., -2.], OrderRel::EQ, 0.);
+        let mut d = lp.to_dict();
+        assert_eq!(d.ll, vec![3, 4, 5]);
+        let z = d.run_simplex(Heuristic::Bland, false).optimum().unwrap();
+        assert!((z + 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_steps() {
+        let steps: Vec<(Step, Dictionary<f64>)> = Simplex::new(make_dict(), Heuristic::Bland).collect();
+        assert_eq!(steps.iter().map(|s| s.0).collect::<Vec<_>>(),
+                   vec![Step::Continue(1, 1), Step::Continue(1, 2)]);
+        assert_eq!(steps[0].1.obj[0], 12.);
+        assert_eq!(steps[1].1.obj[0], 24.);
+    }
+
+    #[test]
+    fn test_chosen_pivot() {
+        let mut s = Simplex::new(make_dict(), Heuristic::Dumb);
+        assert_eq!(s.next_pivot(), Step::Continue(1, 2));
+        assert!(s.pivot(0, 2).is_err()); // x_3 does not decrease with x_2
+        s.pivot(1, 1).unwrap();
+        assert_eq!(s.dictionary().ll, vec![3, 1]);
+        while s.step().is_some() {}
+        assert_eq!(s.result(), Some(SolveResult::Optimal(24.)));
+        assert!(s.pivot(0, 1).is_err());
+    }
+
+    #[test]
+    fn test_chosen_pivot_first_phase() {
+        // max x_1 st x_1 <= 8 and x_1 >= 1
+        let d = Dictionary {
+            m: Matrix { h: 2, w: 2, m: vec![8., -1., -1., 1.] },
+            ll: vec![2, 3],
+            lc: vec![0, 1],
+            obj: vec![0., 1.],
+            weq: vec![0., 0.],
+            var_name: "x",
+        };
+        let mut s = Simplex::new(d, Heuristic::Bland);
+        assert_eq!(s.next_pivot(), Step::Continue(1, 2));
+        let aux = s.dictionary().clone();
+        assert!(s.pivot(0, 2).is_err());
+        assert_eq!(s.dictionary(), &aux);
+        s.pivot(1, 2).unwrap();
+
+        // x_2 = 9 - 2 x_1 + x_3 and x_0 = 1 - x_1 + x_3
+        assert_eq!(s.dictionary().ll, vec![2, FIRST_PHASE_IDX]);
+        assert!(s.pivot(0, 1).is_err());
+        s.pivot(1, 1).unwrap();
+        assert_eq!(s.phase(), Phase::First);
+        while s.step().is_some() {}
+        assert_eq!(s.result(), Some(SolveResult::Optimal(8.)));
+    }
+
+    #[test]
+    fn test_abort() {
+        // max x st x >= 1, x <= 2
+        let d = Dictionary {
+            m: Matrix { h: 2, w: 2, m: vec![-1., 1., 2., -1.] },
+            ll: vec![2, 3],
+            lc: vec![0, 1],
+            obj: vec![0., 1.],
+            weq: vec![0., 0.],
+            var_name: "x",
+        };
+        let mut s = Simplex::new(d.clone(), Heuristic::Bland);
+        assert_eq!(s.phase(), Phase::First);
+        s.step();
+        assert_eq!(s.result(), None);
+        assert_eq!(s.into_dictionary(), d);
     }
 }
+
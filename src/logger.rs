@@ -0,0 +1,42 @@
+//! A minimal logger for the `log` crate, which writes on stderr.
+
+use log::{self, Log, Metadata, Record, LevelFilter};
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Shows the records up to `level`. Another logger may already be installed,
+/// in which case only the level is changed.
+pub fn init(level: LevelFilter) {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_level() {
+        init(LevelFilter::Info);
+        assert!(log_enabled!(Level::Info) && !log_enabled!(Level::Debug));
+        init(LevelFilter::Off);
+        assert!(!log_enabled!(Level::Error));
+    }
+}
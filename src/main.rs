@@ -1,18 +1,21 @@
-extern crate num;
 extern crate getopts;
-
-#[allow(dead_code)]
-#[allow(unused_must_use)]
-
-mod linear_system;
-mod parser;
+#[macro_use]
+extern crate log;
+extern crate simplex;
 
 use std::fs::File;
 use std::path::Path;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::env;
+use std::process;
+use std::str::FromStr;
+use std::time::Instant;
 
 use getopts::{Options};
+use simplex::{linear_system, parser, interior_point, crossover, basis, branch_bound, gomory};
+use simplex::{presolve, scaling, iis, relax, dual, standard_form, logger};
+use simplex::{observer, stats, output, validate};
+use simplex::error::Error;
 
 fn print_latex_header() {
     println!(r"\documentclass[9pt]{{article}}");
@@ -39,73 +42,526 @@ fn print_latex_footer() {
     println!(r"\end{{document}}");
 }
 
+fn print_solution<F: linear_system::OrdField>(x: linear_system::SolveResult<F>,
+                                              kind: linear_system::ObjectiveKind, latex: bool,
+                                              values: &[(usize, F)]) {
+    let opt = match x {
+        linear_system::SolveResult::Optimal(opt) => opt,
+        // In LaTeX, the first phase already says it
+        linear_system::SolveResult::Infeasible if !latex => {
+            println!("The simplex is not feasible!");
+            return;
+        },
+        linear_system::SolveResult::Unbounded if !latex => {
+            println!("This LP is unbounded!");
+            return;
+        },
+        _ => return,
+    };
+    let opt = match kind {
+        linear_system::Maximize => opt,
+        linear_system::Minimize => F::zero() - opt,
+    };
+    if latex {
+        println!("The optimum is ${}$\n", opt);
+    } else {
+        println!("The optimum is {:.10}\n", opt);
+    }
+
+    println!("Values of non-nil variables: \n");
+    for &(label, value) in values.iter() {
+        if value == F::zero() { continue; }
+        if latex {
+            println!("$x_{{ {} }} = {}$\n", label, value);
+        } else {
+            println!("x_{} = {}\n", label, value);
+        }
+    }
+}
+
+/// Prints the solution of `lp` in `format`, json or csv, and writes the
+/// report of GLPK to `report_path`.
+fn print_output(format: Option<&str>, report_path: Option<&str>, lp: &parser::LinearProgram,
+                name: &str, report: &output::Report, stats: Option<&stats::Stats<f64>>)
+                -> Result<(), Error> {
+    if let Some(path) = report_path {
+        write_file(path, &report.to_glpk(lp, name))?;
+    }
+    match format {
+        Some("json") => print!("{}", report.to_json(stats)),
+        Some(_) => print!("{}", report.to_csv()),
+        None => (),
+    }
+    Ok(())
+}
+
+/// The dual values and reduced costs of `input`, read from an optimal
+/// dictionary `d` of the standard form `sf` of `lp`, which is `input` once
+/// presolved if `post` is given.
+fn marginals_of(sf: &standard_form::StandardForm, d: &linear_system::Dictionary<f64>,
+                input: &parser::LinearProgram, lp: &parser::LinearProgram,
+                post: Option<&presolve::Postsolve>) -> (Vec<f64>, Vec<f64>) {
+    let (duals, reduced_costs) = sf.marginals(lp.ineqs.len(), d);
+    match post {
+        Some(post) => post.postsolve_marginals(input, &duals),
+        None => (duals, reduced_costs),
+    }
+}
+
+fn print_iis(lp: &parser::LinearProgram, loc: &parser::Locations,
+             heur: linear_system::Heuristic) -> Result<(), Error> {
+    match iis::iis(lp, heur)? {
+        Some(set) => {
+            println!("Irreducible infeasible subsystem:\n");
+            for c in set {
+                println!("{}\n", iis::describe(lp, Some(loc), c));
+            }
+        },
+        None => println!("The linear relaxation is feasible\n"),
+    }
+    Ok(())
+}
+
+fn print_relaxation(lp: &parser::LinearProgram, loc: &parser::Locations, bound_weight: f64,
+                    heur: linear_system::Heuristic) -> Result<(), Error> {
+    let r = relax::relax(lp, |c| match c {
+        iis::Constraint::Row(_) => 1.0,
+        _ => bound_weight,
+    }, heur)?;
+    if r.violations.is_empty() {
+        println!("The program is feasible, no constraint has to be relaxed\n");
+    } else {
+        println!("The total weighted violation is {:.10}\n", r.total);
+        for &(c, v) in r.violations.iter() {
+            println!("{} relaxed by {}\n", iis::describe(lp, Some(loc), c), v);
+        }
+    }
+
+    println!("Values of non-nil variables: \n");
+    for &(label, value) in r.values.iter() {
+        if value != 0.0 {
+            println!("x_{} = {}\n", label, value);
+        }
+    }
+    Ok(())
+}
+
+/// Solves the dual of `lp` and compares its optimum with the one of `lp`.
+fn solve_dual(lp: &parser::LinearProgram, dual: &parser::LinearProgram,
+              heur: linear_system::Heuristic) -> Result<(), Error> {
+    use linear_system::SolveResult::*;
+    let user_sense = |kind, z: f64| match kind {
+        linear_system::Maximize => z,
+        linear_system::Minimize => -z,
+    };
+
+    let mut d = dual.clone().to_dict()?;
+    let y = d.run_simplex(heur, false);
+    let values: Vec<(usize, f64)> = (0..d.h()).map(|i| (d.ll[i], d.m.at(i, 0))).collect();
+    print_solution(y, dual.goal, false, &values);
+
+    let x = lp.clone().to_dict()?.run_simplex(heur, false);
+    match (x, y) {
+        (Optimal(p), Optimal(q)) => {
+            let (p, q) = (user_sense(lp.goal, p), user_sense(dual.goal, q));
+            if (p - q).abs() <= 1e-6 * p.abs().max(1.0) {
+                println!("Strong duality holds: both optima are {:.10}", p);
+            } else {
+                println!("Strong duality does not hold: the primal optimum is {:.10}, the dual one {:.10}",
+                         p, q);
+            }
+        },
+        (x, y) => println!("The primal is {:?} and the dual is {:?}", x, y),
+    }
+    Ok(())
+}
+
+fn read_file(path: &str) -> Result<String, Error> {
+    let mut src = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut src))
+        .map(|_| src)
+        .map_err(|why| Error::Io(path.to_string(), why))
+}
+
+fn write_file(path: &str, contents: &str) -> Result<(), Error> {
+    File::create(path)
+        .and_then(|mut f| f.write_all(contents.as_bytes()))
+        .map_err(|why| Error::Io(path.to_string(), why))
+}
+
+/// The value of the option `name`, which must be a valid `T` if it is given.
+fn parse_opt<T: FromStr>(matches: &getopts::Matches, name: &str) -> Result<Option<T>, Error> {
+    match matches.opt_str(name) {
+        None => Ok(None),
+        Some(v) => v.parse().map(Some)
+            .map_err(|_| Error::Usage(format!("invalid value {} for --{}", v, name))),
+    }
+}
+
+fn path_stem(path: &str) -> String {
+    Path::new(path).file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
 fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<(), Error> {
     let args: Vec<_> = env::args().collect();
 
     let mut opts = Options::new();
     opts.optflag("b", "bland", "Use Bland's rule");
+    opts.optflagmulti("v", "verbose", "Log the phases on stderr, and every pivot with -vv");
+    opts.optflag("q", "quiet", "Only log the errors");
     opts.optflag("l", "latex", "Print the steps in LaTeX");
-    let matches = match opts.parse(&args[1..]) {
-        Ok(m) => m,
-        Err(err) => {
-            println!("{}", err.to_string());
-            return;
-        },
-    };
+    opts.optflag("i", "interior", "Use the primal-dual interior-point method");
+    opts.optflag("c", "crossover", "Recover an optimal basis from the interior solution");
+    opts.optopt("w", "warm-start", "Start the simplex from the basis saved in FILE", "FILE");
+    opts.optopt("s", "save-basis", "Save the final basis in FILE", "FILE");
+    opts.optopt("", "read-basis", "Start the simplex from the MPS basis in FILE", "FILE");
+    opts.optopt("", "write-basis", "Write the final basis in FILE in the MPS format", "FILE");
+    opts.optflag("", "depth-first", "Explore the branch-and-bound tree depth first");
+    opts.optopt("", "max-nodes", "Stop the branch-and-bound after N nodes", "N");
+    opts.optflag("", "no-heuristics", "Do not look for a first integer solution by rounding and diving");
+    opts.optopt("", "cuts", "Add N rounds of Gomory cuts at the root of the branch-and-bound", "N");
+    opts.optflag("", "cutting-plane", "Solve integer programs with Gomory cuts only");
+    opts.optflag("", "iis", "Find an irreducible infeasible subsystem if the program is infeasible");
+    opts.optflag("", "relax", "Find the smallest violation of the constraints making the program feasible");
+    opts.optopt("", "relax-bound-weight", "Cost of relaxing a bound relative to a constraint (default 1)",
+                "W");
+    opts.optflag("", "dual", "Solve the dual program and check strong duality");
+    opts.optopt("", "write-dual", "Write the dual program in FILE", "FILE");
+    opts.optflag("", "standard-form", "Print the program in the form max c x, A x <= b, x >= 0");
+    opts.optflag("", "stats", "Print statistics on the run of the simplex");
+    opts.optopt("o", "output", "Write a report on the solution to FILE, as GLPK does", "FILE");
+    opts.optopt("", "output-format", "Print the solution as json or csv", "FORMAT");
+    opts.optflag("p", "presolve", "Simplify the program before solving it");
+    opts.optopt("", "scaling", "Scale the constraint matrix with METHOD (geometric or equilibration)",
+                "METHOD");
+    opts.optflag("", "rational", "Use exact rational arithmetic for the cutting planes");
+    let matches = opts.parse(&args[1..]).map_err(|err| Error::Usage(err.to_string()))?;
 
     if matches.free.len() != 1 {
-        println!("USAGE: cargo run [--release] -- [-bl] file.lp");
-        return;
+        return Err(Error::Usage("USAGE: cargo run [--release] -- [-blicpq] [-v...] [--stats] [--output-format FORMAT] [-o FILE] [-w FILE] [-s FILE] file.lp".to_string()));
     }
 
-    let path = Path::new(&matches.free[0]);
-    let mut file = match File::open(&path) {
-        Err(why) => panic!("Could not open file because: {}", why),
-        Ok(file) => file,
-    };
+    logger::init(if matches.opt_present("q") {
+        log::LevelFilter::Error
+    } else {
+        match matches.opt_count("v") {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    });
 
-    let mut src = String::new();
-    let _ = file.read_to_string(&mut src);
-    let mut lp = parser::Parser::parse_lp(&src);
+    let src = read_file(&matches.free[0])?;
+    let (mut lp, locations) = parser::Parser::parse_lp_with_locations(&src)?;
+    let issues = validate::validate(&lp, Some(&locations));
+    let mut errors = 0;
+    for issue in issues.iter() {
+        match issue.severity {
+            validate::Severity::Warning => warn!("{}", issue),
+            validate::Severity::Error => {
+                error!("{}", issue);
+                errors += 1;
+            },
+        }
+    }
+    if errors > 0 {
+        return Err(Error::Model(format!("{} errors in {}", errors, matches.free[0])));
+    }
 
     let kind = lp.goal;
-
-    let mut d = lp.to_dict();
+    let latex = matches.opt_present("l");
     let heur = if matches.opt_present("b") {
         linear_system::Heuristic::Bland
     } else {
         linear_system::Heuristic::Dumb
     };
-    let latex = matches.opt_present("l");
+    let iis_lp = if matches.opt_present("iis") { Some(lp.clone()) } else { None };
+    let format = matches.opt_str("output-format");
+    let report_path = matches.opt_str("o");
+    let name = path_stem(&matches.free[0]);
+    match format.as_ref().map(|f| &f[..]) {
+        None | Some("json") | Some("csv") => (),
+        Some(f) => return Err(Error::Usage(format!("unknown output format {}", f))),
+    }
+    if format.is_some() && latex {
+        return Err(Error::Usage("--output-format cannot be used with --latex".to_string()));
+    }
+    if (format.is_some() || report_path.is_some()) && matches.opt_present("rational") {
+        let msg = "--output-format and --output cannot be used with --rational";
+        return Err(Error::Usage(msg.to_string()));
+    }
+    if matches.opt_present("w") && matches.opt_present("read-basis") {
+        return Err(Error::Usage("--warm-start cannot be used with --read-basis".to_string()));
+    }
+    let max_nodes: Option<usize> = parse_opt(&matches, "max-nodes")?;
+    let root_cuts: Option<usize> = parse_opt(&matches, "cuts")?;
+    let bound_weight: f64 = parse_opt(&matches, "relax-bound-weight")?.unwrap_or(1.0);
+    if !bound_weight.is_finite() || bound_weight < 0.0 {
+        let msg = format!("--relax-bound-weight must be a non-negative number, not {}", bound_weight);
+        return Err(Error::Usage(msg));
+    }
+    let input = lp.clone();
+
+    if matches.opt_present("standard-form") {
+        print!("{}", standard_form::standard_form(&lp)?.report(&lp));
+        return Ok(());
+    }
+
+    if matches.opt_present("relax") {
+        return print_relaxation(&lp, &locations, bound_weight, heur);
+    }
+
+    if matches.opt_present("dual") || matches.opt_present("write-dual") {
+        let dual = dual::dual(&lp)?;
+        if let Some(path) = matches.opt_str("write-dual") {
+            write_file(&path, &dual.to_string())?;
+        }
+        if matches.opt_present("dual") {
+            return solve_dual(&lp, &dual, heur);
+        }
+    }
+
+    let start = Instant::now();
+    // The exact computations are done on the program as it is written
+    let post = if matches.opt_present("p") && !matches.opt_present("rational") {
+        match presolve::presolve(&lp)? {
+            presolve::Presolved::Reduced(reduced, post) => {
+                if !latex && format.is_none() {
+                    println!("Presolve: removed {} rows and {} columns\n",
+                             post.rows_removed, post.cols_removed);
+                }
+                lp = reduced;
+                Some(post)
+            },
+            presolve::Presolved::Infeasible(why) => {
+                let report = output::Report::new(&input, linear_system::SolveResult::Infeasible, &[]);
+                print_output(format.as_ref().map(|f| &f[..]), report_path.as_ref().map(|p| &p[..]),
+                             &input, &name, &report, None)?;
+                if format.is_some() {
+                    info!("The presolve found that the program is not feasible: {}", why);
+                    return Ok(());
+                }
+                println!("The presolve found that the program is not feasible: {}", why);
+                if let Some(ref lp) = iis_lp {
+                    print_iis(lp, &locations, heur)?;
+                }
+                return Ok(());
+            },
+        }
+    } else {
+        None
+    };
+
+    let sf = standard_form::standard_form(&lp)?;
+    let mut d = sf.lp.to_dict();
+    let integers = lp.integer_labels()?;
+    let warm_start = if let Some(path) = matches.opt_str("w") {
+        Some(read_file(&path).and_then(|src| basis::Basis::parse(&src)))
+    } else {
+        matches.opt_str("read-basis").map(|path| {
+            read_file(&path).and_then(|src| basis::read_bas(&lp, &src))
+        })
+    };
+    if let Some(b) = warm_start {
+        if let Err(why) = b.and_then(|b| d.set_basis(&b)) {
+            warn!("Could not warm start, starting from the slack basis: {}", why);
+        }
+    }
+
+    let scaling_method = match matches.opt_str("scaling").as_ref().map(|m| &m[..]) {
+        None => None,
+        Some("geometric") => Some(scaling::ScalingMethod::GeometricMean),
+        Some("equilibration") => Some(scaling::ScalingMethod::Equilibration),
+        Some(m) => return Err(Error::Usage(format!("unknown scaling method {}", m))),
+    };
+    // Scaling would break the integrality of the variables, and of the slacks
+    // which the cutting planes use
+    let cutting_plane = matches.opt_present("cutting-plane");
+    if scaling_method.is_some() && (!integers.is_empty() || cutting_plane) {
+        warn!("The program is not scaled: scaling breaks the integrality used by the cuts and the branching");
+    }
+    let scaling = scaling_method.filter(|_| integers.is_empty() && !cutting_plane).map(|method| {
+        let s = scaling::Scaling::new(&d, method);
+        s.scale(&mut d);
+        s
+    });
+
     if latex {
         print_latex_header();
         println!("This is the initial dictionary: {}\n", d);
     }
-    let x = d.run_simplex(heur, latex);
 
-    if let Some(opt) = x {
-        let opt = match kind {
-            linear_system::Maximize => opt,
-            linear_system::Minimize => -opt,
-        };
-        if latex {
-            println!("The optimum is ${}$\n", opt);
+    if cutting_plane {
+        let mut cut_integers = integers.clone();
+        cut_integers.extend(gomory::integral_slacks(&d, &integers));
+        let cut_kind = gomory::best_cut_kind(&d, &cut_integers);
+        if matches.opt_present("rational") {
+            match gomory::to_rational(&d) {
+                Some(mut d) => {
+                    let (x, rounds) = gomory::cutting_plane(&mut d, &mut cut_integers, cut_kind,
+                                                            1000, heur, latex)?;
+                    println!("{} rounds of {:?} cuts\n", rounds, cut_kind);
+                    let values: Result<Vec<_>, Error> = (0..d.h()).map(|i| {
+                        let shift = gomory::Rational::approximate_float(sf.shift(d.ll[i]))
+                            .ok_or_else(|| {
+                                let msg = format!("the shift of x_{} cannot be written as a rational",
+                                                  d.ll[i]);
+                                Error::Numerical(msg)
+                            })?;
+                        Ok((d.ll[i], d.m.at(i, 0) + shift))
+                    }).collect();
+                    print_solution(x, kind, latex, &values?);
+                },
+                None => {
+                    let msg = "the program cannot be written with rationals";
+                    return Err(Error::Numerical(msg.to_string()));
+                },
+            }
         } else {
-            println!("The optimum is {:.10}\n", opt);
+            let (x, rounds) = gomory::cutting_plane(&mut d, &mut cut_integers, cut_kind,
+                                                    1000, heur, latex)?;
+            let values: Vec<_> = (0..d.h()).map(|i| (d.ll[i], d.m.at(i, 0))).collect();
+            let mut values = sf.unshift(&values);
+            if let Some(ref post) = post {
+                values = post.postsolve(&values);
+            }
+            let report = output::Report::new(&input, x, &values);
+            print_output(format.as_ref().map(|f| &f[..]), report_path.as_ref().map(|p| &p[..]),
+                         &input, &name, &report, None)?;
+            if format.is_none() {
+                println!("{} rounds of {:?} cuts\n", rounds, cut_kind);
+                print_solution(x, kind, latex, &values);
+            }
+        }
+        if latex {
+            print_latex_footer();
         }
+        return Ok(());
+    }
 
-        println!("Values of non-nil variables: \n");
-        for i in 0..d.h() {
-            if d.m.at(i, 0) == 0.0 { continue; }
-            if latex {
-                println!("$x_{{ {} }} = {}$\n", d.ll[i], d.m.at(i, 0));
-            } else {
-                println!("x_{} = {}\n", d.ll[i], d.m.at(i, 0));
+    let mut stats = None;
+    let mut marginals = None;
+    let (x, values) = if matches.opt_present("i") {
+        let mut sol = interior_point::InteriorPoint::new().solve(&d);
+        if matches.opt_present("c") && sol.result.optimum().is_some() {
+            let (res, x) = crossover::crossover(&d, &sol.x, &sol.z, heur, latex)?;
+            d = res;
+            if let Some(ref s) = scaling {
+                s.unscale(&mut d);
+            }
+            marginals = Some(marginals_of(&sf, &d, &input, &lp, post.as_ref()));
+            let values: Vec<(usize, f64)> = (0..d.h()).map(|i| (d.ll[i], d.m.at(i, 0))).collect();
+            (x, values)
+        } else {
+            if let Some(ref s) = scaling {
+                s.unscale_ipm(&d, &mut sol);
             }
+            let values: Vec<(usize, f64)> = sol.x.iter().cloned().enumerate().skip(1).collect();
+            (sol.result, values)
+        }
+    } else if !integers.is_empty() {
+        let mut bb = branch_bound::BranchAndBound::new();
+        bb.heur = heur;
+        if matches.opt_present("depth-first") {
+            bb.selection = branch_bound::NodeSelection::DepthFirst;
+        }
+        if let Some(n) = max_nodes {
+            bb.max_nodes = n;
+        }
+        if let Some(n) = root_cuts {
+            bb.root_cuts = n;
+        }
+        if matches.opt_present("no-heuristics") {
+            bb.heuristics = None;
+        }
+        let sol = bb.solve(&d, &integers)?;
+        let bound = match kind {
+            linear_system::Maximize => sol.bound,
+            linear_system::Minimize => -sol.bound,
+        };
+        let gap = if sol.result.optimum().is_some() {
+            format!("gap {:.4}%", 100.0 * sol.gap())
+        } else {
+            "no integer solution".to_string()
+        };
+        if let (Some(z), None) = (sol.heuristic, &format) {
+            println!("Heuristic solution: {}\n", match kind {
+                linear_system::Maximize => z,
+                linear_system::Minimize => -z,
+            });
+        }
+        if format.is_none() {
+            println!("Branch-and-bound: {} nodes, best bound {}, {}{}\n", sol.nodes, bound, gap,
+                     if sol.limit_reached { " (node limit reached)" } else { "" });
+        }
+        let n_labels = d.w() + d.h();
+        let values: Vec<(usize, f64)> = sol.values.iter().cloned().enumerate()
+            .take(n_labels).skip(1).collect();
+        (sol.result, values)
+    } else {
+        let mut st = stats::Stats::new(&d);
+        let x = if latex {
+            d.run_simplex_with(heur, &mut (observer::LatexObserver::new(), &mut st))
+        } else {
+            d.run_simplex_with(heur, &mut st)
+        };
+        stats = Some(st);
+        if let Some(ref s) = scaling {
+            s.unscale(&mut d);
+        }
+        marginals = Some(marginals_of(&sf, &d, &input, &lp, post.as_ref()));
+        let values: Vec<(usize, f64)> = (0..d.h()).map(|i| (d.ll[i], d.m.at(i, 0))).collect();
+        (x, values)
+    };
+
+    info!("Solved in {:.3?}", start.elapsed());
+
+    if let Some(path) = matches.opt_str("s") {
+        write_file(&path, &d.basis().to_string())?;
+    }
+    if let Some(path) = matches.opt_str("write-basis") {
+        write_file(&path, &basis::write_bas(&lp, &d.basis(), &name)?)?;
+    }
+
+    let values = sf.unshift(&values);
+    let values = match post {
+        Some(ref post) => post.postsolve(&values),
+        None => values,
+    };
+    let mut report = output::Report::new(&input, x, &values);
+    if let Some((duals, reduced_costs)) = marginals {
+        report.set_marginals(&duals, &reduced_costs);
+    }
+    print_output(format.as_ref().map(|f| &f[..]), report_path.as_ref().map(|p| &p[..]),
+                 &input, &name, &report, stats.as_ref())?;
+    if format.is_some() {
+        return Ok(());
+    }
+    let infeasible = x == linear_system::SolveResult::Infeasible;
+    print_solution(x, kind, latex, &values);
+    if let (true, Some(ref lp)) = (infeasible, &iis_lp) {
+        print_iis(lp, &locations, heur)?;
+    }
+    if matches.opt_present("stats") {
+        match stats {
+            Some(ref st) => print!("\nStatistics:\n{}", st),
+            None => println!("\nNo statistics: they are only collected by the simplex"),
         }
     }
 
     if latex {
         print_latex_footer();
     }
+    Ok(())
 }
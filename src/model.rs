@@ -0,0 +1,283 @@
+//! Building and solving programs from Rust code, without the LP format.
+//!
+//! ```
+//! use simplex::linear_system::{Maximize, OrderRel};
+//! use simplex::model::Model;
+//!
+//! let mut m = Model::new();
+//! let x = m.add_var("x", 0.0, Some(3.0));
+//! let y = m.add_var("y", 0.0, None);
+//! m.add_constraint(&[(1.0, x), (2.0, y)], OrderRel::LT, 8.0);
+//! m.set_objective(Maximize, &[(3.0, x), (8.0, y)]);
+//! let sol = m.solve().unwrap();
+//! assert_eq!(sol.objective(), Some(32.0));
+//! assert_eq!(sol.value(y), Some(4.0));
+//! ```
+
+use linear_system::*;
+use parser::{LinearProgram, PInequation, PBound};
+use standard_form::standard_form;
+use branch_bound::BranchAndBound;
+use expr::{LinExpr, LinConstraint};
+use validate::{validate, Severity};
+use error::Error;
+
+/// A variable of a `Model`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Var(usize);
+
+impl Var {
+    /// Index of the variable, in the order in which they were added.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Model {
+    pub heur: Heuristic,
+    goal: ObjectiveKind,
+    obj: Vec<(f64, String)>,
+    obj_cst: f64, // in the sense of the objective
+    vars: Vec<String>,
+    bounds: Vec<PBound>,
+    ineqs: Vec<PInequation<f64>>,
+    integers: Vec<String>,
+    foreign: Option<usize>, // a variable which was used without being added
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    pub result: SolveResult<f64>, // the optimum in the sense of the objective
+    pub values: Vec<f64>, // indexed by `Var::index`, empty without optimum
+}
+
+impl Solution {
+    pub fn objective(&self) -> Option<f64> {
+        self.result.optimum()
+    }
+
+    /// `None` if there is no optimum.
+    pub fn value(&self, x: Var) -> Option<f64> {
+        self.values.get(x.0).cloned()
+    }
+}
+
+impl Default for Model {
+    fn default() -> Model {
+        Model::new()
+    }
+}
+
+impl Model {
+    /// An empty model, which maximizes 0.
+    pub fn new() -> Model {
+        Model {
+            heur: Heuristic::Dumb,
+            goal: Maximize,
+            obj: Vec::new(),
+            obj_cst: 0.0,
+            vars: Vec::new(),
+            bounds: Vec::new(),
+            ineqs: Vec::new(),
+            integers: Vec::new(),
+            foreign: None,
+        }
+    }
+
+    /// Adds the variable `lower <= name <= upper`.
+    pub fn add_var(&mut self, name: &str, lower: f64, upper: Option<f64>) -> Var {
+        self.vars.push(name.to_string());
+        if lower != 0.0 || upper.is_some() {
+            self.bounds.push(PBound { var: name.to_string(), upper, lower: Some(lower) });
+        }
+        Var(self.vars.len() - 1)
+    }
+
+    /// Adds a variable which must take an integral value.
+    pub fn add_integer_var(&mut self, name: &str, lower: f64, upper: Option<f64>) -> Var {
+        self.integers.push(name.to_string());
+        self.add_var(name, lower, upper)
+    }
+
+    /// Adds the constraint `sum a x (kind) cst`, and returns its index.
+    pub fn add_constraint(&mut self, prods: &[(f64, Var)], kind: OrderRel, cst: f64) -> usize {
+        let prods = self.prods(prods);
+        self.ineqs.push(PInequation { prods, kind, cst });
+        self.ineqs.len() - 1
+    }
+
+    pub fn set_objective(&mut self, goal: ObjectiveKind, prods: &[(f64, Var)]) {
+        self.goal = goal;
+        self.obj = self.prods(prods);
+        self.obj_cst = 0.0;
+    }
+
+    /// Adds a constraint written with `LinExpr`, and returns its index.
+    pub fn add(&mut self, c: LinConstraint) -> usize {
+        self.add_constraint(&c.prods, c.kind, c.cst)
+    }
+
+    pub fn maximize<E: Into<LinExpr>>(&mut self, e: E) {
+        self.set_objective_expr(Maximize, e.into());
+    }
+
+    pub fn minimize<E: Into<LinExpr>>(&mut self, e: E) {
+        self.set_objective_expr(Minimize, e.into());
+    }
+
+    fn set_objective_expr(&mut self, goal: ObjectiveKind, e: LinExpr) {
+        self.set_objective(goal, &e.prods);
+        self.obj_cst = e.cst;
+    }
+
+    /// `None` if `x` was not added to this model.
+    pub fn name(&self, x: Var) -> Option<&str> {
+        self.vars.get(x.0).map(|s| &s[..])
+    }
+
+    /// The terms `prods` with the names of the variables. The variables which
+    /// were not added to this model are left out, and `solve` fails.
+    fn prods(&mut self, prods: &[(f64, Var)]) -> Vec<(f64, String)> {
+        let mut res = Vec::new();
+        for &(a, x) in prods.iter() {
+            match self.vars.get(x.0) {
+                Some(name) => res.push((a, name.clone())),
+                None => {
+                    self.foreign.get_or_insert(x.0);
+                },
+            }
+        }
+        res
+    }
+
+    /// The program as if it had been parsed.
+    pub fn to_lp(&self) -> LinearProgram {
+        let mut lp = LinearProgram::new(self.goal, self.obj.clone(), self.ineqs.clone(),
+                                        self.bounds.clone(), self.integers.clone(), self.vars.clone());
+        lp.obj_cst = match self.goal {
+            Maximize => self.obj_cst,
+            Minimize => -self.obj_cst,
+        };
+        lp
+    }
+
+    /// Solves the model with the simplex, or the branch-and-bound if it has
+    /// integer variables.
+    pub fn solve(&self) -> Result<Solution, Error> {
+        if let Some(j) = self.foreign {
+            return Err(Error::Model(format!("the variable {} was not added to the model", j)));
+        }
+        let lp = self.to_lp();
+        for issue in validate(&lp, None) {
+            match issue.severity {
+                Severity::Warning => warn!("{}", issue),
+                Severity::Error => return Err(Error::Model(issue.message)),
+            }
+        }
+        let sf = standard_form(&lp)?;
+        let mut d = sf.lp.to_dict();
+        let (result, values) = if self.integers.is_empty() {
+            let result = d.run_simplex(self.heur, false);
+            (result, d.values())
+        } else {
+            let mut bb = BranchAndBound::new();
+            bb.heur = self.heur;
+            let sol = bb.solve(&d, &lp.integer_labels()?)?;
+            (sol.result, sol.values)
+        };
+
+        let result = match (self.goal, result) {
+            (Minimize, SolveResult::Optimal(z)) => SolveResult::Optimal(-z),
+            (_, r) => r,
+        };
+        let n = self.vars.len();
+        let values = match result {
+            SolveResult::Optimal(_) => (1..n + 1).map(|l| values[l] + sf.shift(l)).collect(),
+            _ => Vec::new(),
+        };
+        Ok(Solution { result, values })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    #[test]
+    fn test_same_as_parsed() {
+        let mut m = Model::new();
+        let x = m.add_var("x", 1.0, Some(4.0));
+        let y = m.add_var("y", 0.0, None);
+        m.add_constraint(&[(1.0, x), (-2.0, y)], OrderRel::LT, -4.0);
+        m.add_constraint(&[(1.0, x), (1.0, y)], OrderRel::EQ, 10.5);
+        m.set_objective(Minimize, &[(-0.5, x), (3.0, y)]);
+        let expected = Parser::parse_lp("MINIMIZE\n-0.5 x + 3 y\nSUBJECT TO\nx - 2 y <= -4\n\
+                                         x + y = 10.5\nBOUNDS\n1 <= x <= 4\nVARIABLES\nx\ny\n").unwrap();
+        assert_eq!(m.to_lp(), expected);
+
+        // The objective is 31.5 - 3.5 x on the equality
+        let sol = m.solve().unwrap();
+        assert_eq!(sol.objective(), Some(17.5));
+        assert_eq!((sol.value(x), sol.value(y)), (Some(4.0), Some(6.5)));
+    }
+
+    #[test]
+    fn test_integer() {
+        let mut m = Model::new();
+        let x = m.add_integer_var("x", 0.0, None);
+        let y = m.add_integer_var("y", 0.0, None);
+        m.add_constraint(&[(6.0, x), (4.0, y)], OrderRel::LT, 24.0);
+        m.add_constraint(&[(1.0, x), (2.0, y)], OrderRel::LT, 6.0);
+        m.set_objective(Maximize, &[(5.0, x), (4.0, y)]);
+        let sol = m.solve().unwrap();
+        assert_eq!(sol.objective(), Some(20.0));
+        assert_eq!(sol.value(x).unwrap() + sol.value(y).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_infeasible() {
+        let mut m = Model::new();
+        let x = m.add_var("x", 2.0, None);
+        m.add_constraint(&[(1.0, x)], OrderRel::LT, 1.0);
+        let sol = m.solve().unwrap();
+        assert_eq!(sol.result, SolveResult::Infeasible);
+        assert_eq!(sol.value(x), None);
+
+        m.add_var("x", 0.0, None);
+        assert!(m.solve().is_err());
+
+        let mut m = Model::new();
+        let x = m.add_var("x", 0.0, None);
+        m.add_constraint(&[(f64::NAN, x)], OrderRel::LT, 1.0);
+        assert!(m.solve().is_err());
+    }
+
+    #[test]
+    fn test_expressions() {
+        // The same program as in `test_same_as_parsed`, with a constant in the objective
+        let mut m = Model::new();
+        let x = m.add_var("x", 1.0, Some(4.0));
+        let y = m.add_var("y", 0.0, None);
+        m.add((x - 2.0 * y).le(-4.0));
+        m.add((x + y).eq(10.5));
+        m.minimize(3.0 * y - 0.5 * x + 1.0);
+        let sol = m.solve().unwrap();
+        assert_eq!(sol.objective(), Some(18.5));
+        assert_eq!((sol.value(x), sol.value(y)), (Some(4.0), Some(6.5)));
+    }
+
+    #[test]
+    fn test_foreign_var() {
+        let mut other = Model::new();
+        other.add_var("x", 0.0, None);
+        let z = other.add_var("z", 0.0, None);
+
+        let mut m = Model::new();
+        let x = m.add_var("x", 0.0, Some(1.0));
+        assert_eq!((m.name(x), m.name(z)), (Some("x"), None));
+        m.maximize(x + z);
+        assert!(m.solve().is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Hooks called by the simplex as it runs, so that the LaTeX output, logging,
+//! statistics or user code can follow its progress. The solver itself prints
+//! nothing.
+
+use linear_system::*;
+use gomory::Cut;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    First, // minimizes the auxiliary variable to find a feasible basis
+    Second,
+}
+
+/// A pivot of the simplex, once performed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pivot<F> {
+    pub phase: Phase,
+    pub entering: usize, // label
+    pub leaving: usize, // label
+    pub element: F, // coefficient of the entering variable in the line of the leaving one
+    pub objective: F, // value of the objective of the phase after the pivot
+    pub degenerate: bool, // the objective did not change
+}
+
+/// All the methods do nothing by default.
+pub trait Observer<F: OrdField> {
+    /// Called with the first dictionary of the phase.
+    fn phase_start(&mut self, _phase: Phase, _d: &Dictionary<F>) {}
+
+    /// Called after each pivot, with the new dictionary.
+    fn pivot(&mut self, _p: &Pivot<F>, _d: &Dictionary<F>) {}
+
+    /// Called before `pivot` for the pivots which do not change the objective.
+    fn degenerate(&mut self, _p: &Pivot<F>) {}
+
+    /// Called with the last dictionary of the phase.
+    fn phase_end(&mut self, _phase: Phase, _d: &Dictionary<F>) {}
+
+    fn finish(&mut self, _result: &SolveResult<F>) {}
+
+    /// Called when a cutting plane adds `cut` to the dictionary.
+    fn cut(&mut self, _cut: &Cut<F>) {}
+
+    /// Called with the re-optimized dictionary after each round of cuts.
+    fn cut_round(&mut self, _d: &Dictionary<F>) {}
+}
+
+/// Observes nothing.
+pub struct NoObserver;
+
+impl<F: OrdField> Observer<F> for NoObserver {}
+
+impl<F: OrdField, O: Observer<F>> Observer<F> for &mut O {
+    fn phase_start(&mut self, phase: Phase, d: &Dictionary<F>) {
+        (**self).phase_start(phase, d);
+    }
+
+    fn pivot(&mut self, p: &Pivot<F>, d: &Dictionary<F>) {
+        (**self).pivot(p, d);
+    }
+
+    fn degenerate(&mut self, p: &Pivot<F>) {
+        (**self).degenerate(p);
+    }
+
+    fn phase_end(&mut self, phase: Phase, d: &Dictionary<F>) {
+        (**self).phase_end(phase, d);
+    }
+
+    fn finish(&mut self, result: &SolveResult<F>) {
+        (**self).finish(result);
+    }
+
+    fn cut(&mut self, cut: &Cut<F>) {
+        (**self).cut(cut);
+    }
+
+    fn cut_round(&mut self, d: &Dictionary<F>) {
+        (**self).cut_round(d);
+    }
+}
+
+/// Both observers are called in turn.
+impl<F: OrdField, A: Observer<F>, B: Observer<F>> Observer<F> for (A, B) {
+    fn phase_start(&mut self, phase: Phase, d: &Dictionary<F>) {
+        self.0.phase_start(phase, d);
+        self.1.phase_start(phase, d);
+    }
+
+    fn pivot(&mut self, p: &Pivot<F>, d: &Dictionary<F>) {
+        self.0.pivot(p, d);
+        self.1.pivot(p, d);
+    }
+
+    fn degenerate(&mut self, p: &Pivot<F>) {
+        self.0.degenerate(p);
+        self.1.degenerate(p);
+    }
+
+    fn phase_end(&mut self, phase: Phase, d: &Dictionary<F>) {
+        self.0.phase_end(phase, d);
+        self.1.phase_end(phase, d);
+    }
+
+    fn finish(&mut self, result: &SolveResult<F>) {
+        self.0.finish(result);
+        self.1.finish(result);
+    }
+
+    fn cut(&mut self, cut: &Cut<F>) {
+        self.0.cut(cut);
+        self.1.cut(cut);
+    }
+
+    fn cut_round(&mut self, d: &Dictionary<F>) {
+        self.0.cut_round(d);
+        self.1.cut_round(d);
+    }
+}
+
+/// Prints every dictionary and pivot in LaTeX.
+#[derive(Default)]
+pub struct LatexObserver {
+    first_phase: bool,
+}
+
+impl LatexObserver {
+    pub fn new() -> LatexObserver {
+        LatexObserver { first_phase: false }
+    }
+}
+
+impl<F: OrdField> Observer<F> for LatexObserver {
+    fn phase_start(&mut self, phase: Phase, d: &Dictionary<F>) {
+        match phase {
+            Phase::First => {
+                self.first_phase = true;
+                println!("Should we do the first phase? true\n");
+            },
+            Phase::Second if !self.first_phase => println!("Should we do the first phase? false\n"),
+            Phase::Second => println!("Dictionary for the beginning of the second phase:\n"),
+        }
+        println!("{}\n", d);
+    }
+
+    fn pivot(&mut self, p: &Pivot<F>, d: &Dictionary<F>) {
+        println!("Performing pivot: entering {}, leaving {}\n", p.entering, p.leaving);
+        println!("{}\n", d);
+    }
+
+    fn phase_end(&mut self, phase: Phase, d: &Dictionary<F>) {
+        if phase == Phase::First {
+            let res = F::zero() - d.obj[0];
+            println!("The minimum value of the dummy variable is {}\n", res);
+            if res > F::tolerance() {
+                println!("It seems the input dictionary is not feasible!\n");
+            } else {
+                println!("Therefore it is feasible!");
+            }
+        }
+    }
+
+    fn finish(&mut self, result: &SolveResult<F>) {
+        if *result == SolveResult::Unbounded {
+            println!("This LP is unbounded!");
+        }
+    }
+
+    fn cut(&mut self, cut: &Cut<F>) {
+        println!("Adding the cut ${}$\n", cut);
+    }
+
+    fn cut_round(&mut self, d: &Dictionary<F>) {
+        println!("{}\n", d);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Observer<f64> for Recorder {
+        fn phase_start(&mut self, phase: Phase, _d: &Dictionary<f64>) {
+            self.events.push(format!("start {:?}", phase));
+        }
+
+        fn pivot(&mut self, p: &Pivot<f64>, _d: &Dictionary<f64>) {
+            self.events.push(format!("pivot {} {} {}", p.entering, p.leaving, p.objective));
+        }
+
+        fn degenerate(&mut self, _p: &Pivot<f64>) {
+            self.events.push("degenerate".to_string());
+        }
+
+        fn phase_end(&mut self, phase: Phase, _d: &Dictionary<f64>) {
+            self.events.push(format!("end {:?}", phase));
+        }
+
+        fn finish(&mut self, result: &SolveResult<f64>) {
+            self.events.push(format!("{:?}", result));
+        }
+
+        fn cut(&mut self, _cut: &Cut<f64>) {
+            self.events.push("cut".to_string());
+        }
+
+        fn cut_round(&mut self, _d: &Dictionary<f64>) {
+            self.events.push("round".to_string());
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let mut obs = Recorder::default();
+        let res = make_dict().run_simplex_with(Heuristic::Bland, &mut obs);
+        assert_eq!(res, SolveResult::Optimal(24.0));
+        assert_eq!(obs.events, vec!["start Second", "pivot 1 4 12", "pivot 2 1 24", "end Second",
+                                    "Optimal(24.0)"]);
+    }
+
+    #[test]
+    fn test_first_phase() {
+        // max x st x >= 1, x <= 2
+        let mut d = Dictionary {
+            m: Matrix { h: 2, w: 2, m: vec![-1., 1., 2., -1.] },
+            ll: vec![2, 3],
+            lc: vec![0, 1],
+            obj: vec![0., 1.],
+            weq: vec![0., 0.],
+            var_name: "x",
+        };
+        let mut obs = (Recorder::default(), NoObserver);
+        assert_eq!(d.run_simplex_with(Heuristic::Bland, &mut obs), SolveResult::Optimal(2.0));
+        let events = obs.0.events;
+        // The auxiliary variable enters the basis by the first pivot
+        assert_eq!(&events[..2], &["start First", "pivot 1073741824 2 -1"]);
+        let second = events.iter().position(|e| e == "start Second").unwrap();
+        assert_eq!(events[second - 1], "end First");
+        assert_eq!(events.last().unwrap(), "Optimal(2.0)");
+    }
+
+    #[test]
+    fn test_cuts() {
+        use gomory::{cutting_plane_with, integral_slacks, CutKind};
+        let lp = make_knapsack();
+        let mut d = lp.to_dict().unwrap();
+        let mut integers = lp.integer_labels().unwrap();
+        integers.extend(integral_slacks(&d, &integers));
+        let mut obs = Recorder::default();
+        let (_, rounds) = cutting_plane_with(&mut d, &mut integers, CutKind::Fractional, 50,
+                                             Heuristic::Bland, &mut obs).unwrap();
+        assert!(rounds > 0);
+        assert_eq!(obs.events.iter().filter(|e| *e == "round").count(), rounds);
+        assert!(obs.events.iter().filter(|e| *e == "cut").count() >= rounds);
+    }
+}
@@ -0,0 +1,381 @@
+//! The solution of a program in machine-readable formats, with the names of
+//! its variables and constraints.
+//!
+//! The JSON output is an object with the fields:
+//!
+//! - `version`: 1, incremented when a field changes meaning or is removed;
+//! - `status`: `"optimal"`, `"infeasible"` or `"unbounded"`;
+//! - `objective`: the optimum, or `null`;
+//! - `variables`: `{"name", "value", "reduced_cost"}` for each variable;
+//! - `constraints`: `{"name", "activity", "slack", "dual"}` for each
+//!   constraint, named `c1`, `c2`... in the order of the file;
+//! - `statistics`: the statistics of the simplex (see `stats`), or `null`.
+//!
+//! The values are `null` when there is no optimum, and so are the marginals
+//! when they are not known. The CSV output has the columns
+//! `kind,name,value,slack,marginal`, with one line for the status, one for
+//! the objective, then one per variable and one per constraint.
+//!
+//! `Report::to_glpk` writes the tables of the `--output` file of GLPK. The
+//! status of a row or column is read from its value: `NL` or `NU` when it is
+//! at its lower or upper bound, `NS` for equalities and `B` otherwise, so
+//! that a degenerate basic variable is shown at its bound.
+
+use std::fmt::Write;
+
+use linear_system::*;
+use parser::{LinearProgram, PInequation};
+use stats::Stats;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub value: Option<f64>,
+    pub reduced_cost: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub name: String,
+    pub activity: Option<f64>, // value of the left-hand side
+    pub slack: Option<f64>, // distance to the constant, non-negative when satisfied
+    pub dual: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub result: SolveResult<f64>, // the optimum in the sense of the objective
+    pub columns: Vec<Column>,
+    pub rows: Vec<Row>,
+}
+
+impl Report {
+    /// The solution of `lp`, given the result of the simplex on its dictionary
+    /// and the values of its variables indexed by label. The variables
+    /// missing from `values` are at their lower bound.
+    pub fn new(lp: &LinearProgram, result: SolveResult<f64>, values: &[(usize, f64)]) -> Report {
+        let result = match (lp.goal, result) {
+            (Minimize, SolveResult::Optimal(z)) => SolveResult::Optimal(-z),
+            (_, r) => r,
+        };
+        let optimal = result.optimum().is_some();
+        let value = |j: usize| {
+            let v = values.iter().find(|&&(l, _)| l == j + 1).map(|&(_, v)| v);
+            v.unwrap_or_else(|| lp.bounds.iter().rev().filter(|b| b.var == lp.vars[j])
+                             .find_map(|b| b.lower).unwrap_or(0.0))
+        };
+        let x: Vec<f64> = (0..lp.vars.len()).map(value).collect();
+
+        let columns = lp.vars.iter().zip(x.iter()).map(|(name, &v)| Column {
+            name: name.clone(),
+            value: if optimal { Some(v) } else { None },
+            reduced_cost: None,
+        }).collect();
+        let rows = lp.ineqs.iter().enumerate().map(|(k, ineq)| {
+            let activity: f64 = ineq.prods.iter()
+                .map(|&(a, ref name)| a * lp.vars.iter().position(|y| y == name).map_or(0.0, |j| x[j]))
+                .sum();
+            let slack = match ineq.kind {
+                OrderRel::GT => activity - ineq.cst,
+                _ => ineq.cst - activity,
+            };
+            Row {
+                name: format!("c{}", k + 1),
+                activity: if optimal { Some(activity) } else { None },
+                slack: if optimal { Some(slack) } else { None },
+                dual: None,
+            }
+        }).collect();
+        Report { result, columns, rows }
+    }
+
+    /// Sets the marginals given by `StandardForm::marginals`, if there is an
+    /// optimum.
+    pub fn set_marginals(&mut self, duals: &[f64], reduced_costs: &[f64]) {
+        if self.result.optimum().is_none() {
+            return;
+        }
+        for (r, &y) in self.rows.iter_mut().zip(duals.iter()) {
+            r.dual = Some(y);
+        }
+        for (c, &z) in self.columns.iter_mut().zip(reduced_costs.iter()) {
+            c.reduced_cost = Some(z);
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self.result {
+            SolveResult::Optimal(_) => "optimal",
+            SolveResult::Infeasible => "infeasible",
+            SolveResult::Unbounded => "unbounded",
+        }
+    }
+
+    pub fn to_json(&self, stats: Option<&Stats<f64>>) -> String {
+        let mut s = String::from("{\n");
+        let _ = writeln!(s, "  \"version\": 1,\n  \"status\": \"{}\",", self.status());
+        let _ = writeln!(s, "  \"objective\": {},", json_number(self.result.optimum()));
+        s.push_str("  \"variables\": [");
+        for (k, c) in self.columns.iter().enumerate() {
+            let _ = write!(s, "{}\n    {{\"name\": {}, \"value\": {}, \"reduced_cost\": {}}}",
+                           if k == 0 { "" } else { "," }, json_string(&c.name),
+                           json_number(c.value), json_number(c.reduced_cost));
+        }
+        s.push_str(if self.columns.is_empty() { "],\n" } else { "\n  ],\n" });
+        s.push_str("  \"constraints\": [");
+        for (k, r) in self.rows.iter().enumerate() {
+            let _ = write!(s, "{}\n    {{\"name\": {}, \"activity\": {}, \"slack\": {}, \"dual\": {}}}",
+                           if k == 0 { "" } else { "," }, json_string(&r.name),
+                           json_number(r.activity), json_number(r.slack), json_number(r.dual));
+        }
+        s.push_str(if self.rows.is_empty() { "],\n" } else { "\n  ],\n" });
+        s.push_str("  \"statistics\": ");
+        match stats {
+            Some(st) => {
+                let phase = |p: &Option<::stats::PhaseStats>| match *p {
+                    Some(p) => format!("{{\"pivots\": {}, \"degenerate\": {}, \"seconds\": {}}}",
+                                       p.pivots, p.degenerate, p.time.as_secs_f64()),
+                    None => "null".to_string(),
+                };
+                let _ = writeln!(s, "{{\"lines\": {}, \"columns\": {}, \"nonzeros\": {}, \"density\": {}, \
+                                     \"first_phase\": {}, \"second_phase\": {}, \"min_pivot\": {}, \
+                                     \"max_pivot\": {}}}",
+                                 st.lines, st.columns, st.nonzeros, json_number(Some(st.density())),
+                                 phase(&st.first), phase(&st.second), json_number(st.min_pivot),
+                                 json_number(st.max_pivot));
+            },
+            None => s.push_str("null\n"),
+        }
+        s.push_str("}\n");
+        s
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("kind,name,value,slack,marginal\n");
+        let _ = writeln!(s, "status,{},,,", self.status());
+        let _ = writeln!(s, "objective,,{},,", csv_number(self.result.optimum()));
+        for c in self.columns.iter() {
+            let _ = writeln!(s, "variable,{},{},,{}", csv_string(&c.name), csv_number(c.value),
+                             csv_number(c.reduced_cost));
+        }
+        for r in self.rows.iter() {
+            let _ = writeln!(s, "constraint,{},{},{},{}", csv_string(&r.name), csv_number(r.activity),
+                             csv_number(r.slack), csv_number(r.dual));
+        }
+        s
+    }
+}
+
+impl Report {
+    /// The report of GLPK on the solution of `lp`, named `name`.
+    pub fn to_glpk(&self, lp: &LinearProgram, name: &str) -> String {
+        let mut s = String::new();
+        let nonzeros: usize = lp.ineqs.iter().map(|ineq| ineq.prods.len()).sum();
+        let _ = writeln!(s, "Problem:    {}", name);
+        let _ = writeln!(s, "Rows:       {}", lp.ineqs.len());
+        let _ = writeln!(s, "Columns:    {}", lp.vars.len());
+        let _ = writeln!(s, "Non-zeros:  {}", nonzeros);
+        let _ = writeln!(s, "Status:     {}", self.status().to_uppercase());
+        let _ = writeln!(s, "Objective:  obj = {} ({})\n",
+                         self.result.optimum().map_or("undefined".to_string(), format_g),
+                         match lp.goal { Maximize => "MAXimum", Minimize => "MINimum" });
+
+        s.push_str("   No.   Row name   St   Activity     Lower bound   Upper bound    Marginal\n");
+        s.push_str(TABLE_RULE);
+        for (k, (r, ineq)) in self.rows.iter().zip(lp.ineqs.iter()).enumerate() {
+            let (lower, upper) = row_bounds(ineq);
+            write_line(&mut s, k + 1, &r.name, r.activity, lower, upper, r.dual, ineq.kind == OrderRel::EQ);
+        }
+
+        s.push_str("\n   No. Column name  St   Activity     Lower bound   Upper bound    Marginal\n");
+        s.push_str(TABLE_RULE);
+        for (j, c) in self.columns.iter().enumerate() {
+            let (lower, upper) = column_bounds(lp, j);
+            write_line(&mut s, j + 1, &c.name, c.value, Some(lower), upper, c.reduced_cost, false);
+        }
+        s.push_str("\nEnd of output\n");
+        s
+    }
+}
+
+const TABLE_RULE: &str = "------ ------------ -- ------------- ------------- ------------- -------------\n";
+
+fn row_bounds(ineq: &PInequation<f64>) -> (Option<f64>, Option<f64>) {
+    match ineq.kind {
+        OrderRel::LT => (None, Some(ineq.cst)),
+        OrderRel::GT => (Some(ineq.cst), None),
+        OrderRel::EQ => (Some(ineq.cst), Some(ineq.cst)),
+    }
+}
+
+/// The bounds of `vars[j]` as `standard_form` reads them.
+fn column_bounds(lp: &LinearProgram, j: usize) -> (f64, Option<f64>) {
+    let bounds = lp.bounds.iter().filter(|b| b.var == lp.vars[j]);
+    let lower = bounds.clone().rev().find_map(|b| b.lower).unwrap_or(0.0);
+    let upper = bounds.filter_map(|b| b.upper).fold(None, |u: Option<f64>, v| Some(u.map_or(v, |u| u.min(v))));
+    (lower, upper)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_line(s: &mut String, k: usize, name: &str, value: Option<f64>, lower: Option<f64>,
+              upper: Option<f64>, marginal: Option<f64>, fixed: bool) {
+    let near = |x: f64, b: Option<f64>| b.is_some_and(|b| (x - b).abs() <= 1e-9 * b.abs().max(1.0));
+    let status = match value {
+        None => "",
+        Some(_) if fixed => "NS",
+        Some(x) if near(x, lower) => "NL",
+        Some(x) if near(x, upper) => "NU",
+        Some(_) => "B",
+    };
+    let number = |x: Option<f64>| x.map_or(String::new(), format_g);
+    // As GLPK, long names are alone on their line
+    if name.len() > 12 {
+        let _ = write!(s, "{:>6} {}\n{:19} ", k, name, "");
+    } else {
+        let _ = write!(s, "{:>6} {:<12} ", k, name);
+    }
+    let upper = if fixed { "=".to_string() } else { number(upper) };
+    let marginal = if status == "B" { None } else { marginal.filter(|&m| m != 0.0) };
+    let line = format!("{:<2} {:>13} {:>13} {:>13} {:>13}", status, number(value), number(lower), upper,
+                       number(marginal));
+    let _ = writeln!(s, "{}", line.trim_end());
+}
+
+/// `x` with 6 significant digits, as `%g` in C.
+fn format_g(x: f64) -> String {
+    if x == 0.0 || !x.is_finite() {
+        return x.to_string();
+    }
+    let e = x.abs().log10().floor() as i32;
+    let trim = |m: String| if m.contains('.') {
+        m.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        m
+    };
+    if !(-4..6).contains(&e) {
+        let m = format!("{:.5e}", x);
+        let (mantissa, exp) = m.split_at(m.find('e').unwrap());
+        format!("{}{}", trim(mantissa.to_string()), exp)
+    } else {
+        trim(format!("{:.*}", (5 - e) as usize, x))
+    }
+}
+
+fn json_number(x: Option<f64>) -> String {
+    match x {
+        Some(x) if x.is_finite() => x.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+fn json_string(x: &str) -> String {
+    let mut s = String::from("\"");
+    for c in x.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            c if (c as u32) < 0x20 => { let _ = write!(s, "\\u{:04x}", c as u32); },
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+fn csv_number(x: Option<f64>) -> String {
+    x.map_or(String::new(), |x| x.to_string())
+}
+
+fn csv_string(x: &str) -> String {
+    if x.contains(',') || x.contains('"') {
+        format!("\"{}\"", x.replace('"', "\"\""))
+    } else {
+        x.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+    use standard_form::standard_form;
+
+    fn solve(src: &str) -> Report {
+        let lp = Parser::parse_lp(src).unwrap();
+        let sf = standard_form(&lp).unwrap();
+        let mut d = sf.lp.to_dict();
+        let result = d.run_simplex(Heuristic::Bland, false);
+        let values: Vec<_> = (0..d.h()).map(|i| (d.ll[i], d.m.at(i, 0))).collect();
+        let mut report = Report::new(&lp, result, &sf.unshift(&values));
+        let (duals, reduced) = sf.marginals(lp.ineqs.len(), &d);
+        report.set_marginals(&duals, &reduced);
+        report
+    }
+
+    #[test]
+    fn test_report() {
+        // x is non-basic at its lower bound 1
+        let report = solve("MINIMIZE\n2 x + y\nSUBJECT TO\nx + y >= 3\nx - y <= 0\n\
+                            BOUNDS\nx >= 1\nVARIABLES\nx\ny\n");
+        assert_eq!(report.result, SolveResult::Optimal(4.0));
+        assert_eq!(report.columns[0], Column { name: "x".to_string(), value: Some(1.0), reduced_cost: Some(1.0) });
+        assert_eq!(report.columns[1].value, Some(2.0));
+        assert_eq!(report.rows[0], Row { name: "c1".to_string(), activity: Some(3.0), slack: Some(0.0),
+                                         dual: Some(1.0) });
+        assert_eq!(report.rows[1].slack, Some(1.0));
+    }
+
+    #[test]
+    fn test_formats() {
+        let report = solve("MAXIMIZE\nx\nSUBJECT TO\nx <= 2\nBOUNDS\nVARIABLES\nx\n");
+        assert_eq!(report.to_json(None), "{\n  \"version\": 1,\n  \"status\": \"optimal\",\n  \
+                                          \"objective\": 2,\n  \"variables\": [\n    \
+                                          {\"name\": \"x\", \"value\": 2, \"reduced_cost\": 0}\n  ],\n  \
+                                          \"constraints\": [\n    {\"name\": \"c1\", \"activity\": 2, \
+                                          \"slack\": 0, \"dual\": 1}\n  ],\n  \"statistics\": null\n}\n");
+        assert_eq!(report.to_csv(), "kind,name,value,slack,marginal\nstatus,optimal,,,\nobjective,,2,,\n\
+                                     variable,x,2,,0\nconstraint,c1,2,0,1\n");
+
+        let report = solve("MAXIMIZE\nx\nSUBJECT TO\nx >= 2\nBOUNDS\nVARIABLES\nx\n");
+        assert_eq!(report.status(), "unbounded");
+        assert!(report.to_csv().contains("\nvariable,x,,,\n"));
+        assert_eq!(json_string("a\"b\n"), "\"a\\\"b\\u000a\"");
+    }
+
+    #[test]
+    fn test_glpk() {
+        let lp = Parser::parse_lp("MAXIMIZE\n3 x + 2 y\nSUBJECT TO\nx + y <= 4\nx + 3 y = 6\n\
+                                   BOUNDS\nx <= 3\nVARIABLES\nx\nlong_variable_name\n"
+                                  .replace("y", "long_variable_name").as_str()).unwrap();
+        let sf = standard_form(&lp).unwrap();
+        let mut d = sf.lp.to_dict();
+        let result = d.run_simplex(Heuristic::Bland, false);
+        let values: Vec<_> = (0..d.h()).map(|i| (d.ll[i], d.m.at(i, 0))).collect();
+        let mut report = Report::new(&lp, result, &sf.unshift(&values));
+        let (duals, reduced) = sf.marginals(lp.ineqs.len(), &d);
+        report.set_marginals(&duals, &reduced);
+        assert_eq!(report.to_glpk(&lp, "test"), "\
+Problem:    test
+Rows:       2
+Columns:    2
+Non-zeros:  4
+Status:     OPTIMAL
+Objective:  obj = 11 (MAXimum)
+
+   No.   Row name   St   Activity     Lower bound   Upper bound    Marginal
+------ ------------ -- ------------- ------------- ------------- -------------
+     1 c1           NU             4                           4           3.5
+     2 c2           NS             6             6             =          -0.5
+
+   No. Column name  St   Activity     Lower bound   Upper bound    Marginal
+------ ------------ -- ------------- ------------- ------------- -------------
+     1 x            NU             3             0             3
+     2 long_variable_name
+                    B              1             0
+
+End of output
+");
+        assert_eq!((format_g(1234567.0), format_g(0.000012345), format_g(-2.5)),
+                   ("1.23457e6".to_string(), "1.2345e-5".to_string(), "-2.5".to_string()));
+    }
+}
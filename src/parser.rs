@@ -2,162 +2,161 @@ use std::option::Option;
 use std::str;
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::fmt::{self, Display, Formatter};
 
 use std::option::Option::*;
 
 use linear_system::*;
+use standard_form::standard_form;
+use error::Error;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct PInequation<F: OrdField> {
-    prods: Vec<(F, String)>,
-    kind: OrderRel,
-    cst: F,
+    pub prods: Vec<(F, String)>,
+    pub kind: OrderRel,
+    pub cst: F,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct PBound {
-    var: String,
-    upper: Option<f64>,
-    lower: Option<f64>,
+    pub var: String,
+    pub upper: Option<f64>,
+    pub lower: Option<f64>,
+}
+
+impl<F: OrdField> Display for PInequation<F> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        for (k, &(c, ref x)) in self.prods.iter().enumerate() {
+            if k == 0 {
+                write!(f, "{} {}", c, x)?;
+            } else if c < F::zero() {
+                write!(f, " - {} {}", F::zero() - c, x)?;
+            } else {
+                write!(f, " + {} {}", c, x)?;
+            }
+        }
+        write!(f, " {} {}", self.kind, self.cst)
+    }
+}
+
+/// A bound without sides is written as `x >= 0`: there are no free variables,
+/// every variable keeps the default lower bound 0.
+impl Display for PBound {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match (self.lower, self.upper) {
+            (Some(l), Some(u)) => write!(f, "{} <= {} <= {}", l, self.var, u),
+            (Some(l), None) => write!(f, "{} >= {}", self.var, l),
+            (None, Some(u)) => write!(f, "{} <= {}", self.var, u),
+            (None, None) => write!(f, "{} >= 0", self.var),
+        }
+    }
+}
+
+/// Lines of the file where the parts of a `LinearProgram` are written,
+/// starting at 1.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Locations {
+    pub obj: usize,
+    pub ineqs: Vec<usize>,
+    pub bounds: Vec<usize>, // the bounds of BINARY variables are on their line of the clause
+    pub integers: Vec<usize>,
+    pub vars: Vec<usize>,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct LinearProgram {
     pub obj: Vec<(f64, String)>,
-    pub obj_cst: f64,
+    pub obj_cst: f64, // constant of the maximized objective, as in the dictionary
     pub goal: ObjectiveKind,
     pub ineqs: Vec<PInequation<f64>>,
     pub bounds: Vec<PBound>,
+    pub integers: Vec<String>, // GENERAL and BINARY variables
 
     pub vars: Vec<String>,
     vars_inv: HashMap<String, usize>,
-    dummy_idx: usize,
 }
 
-impl LinearProgram {
-    pub fn to_dict(&mut self) -> Dictionary<f64> {
-        self.normalize_bounds();
-
-        let mut m: Matrix<f64> =
-            Matrix::allocate_zeroed(self.ineqs.len(), self.vars.len() + 1); // +1 for the cst term
-
-        for (i, ineq) in self.ineqs.iter().enumerate() {
-            let mult: f64 = match ineq.kind {
-                OrderRel::EQ => panic!("The equalities must have been deleted before!"),
-                OrderRel::LT => 1.0,
-                OrderRel::GT => -1.0,
-            };
-
-            m.set_at(i, 0, mult * ineq.cst);
-            for &(c_j, ref x_j) in ineq.prods.iter() {
-                let j = self.var_idx(&x_j);
-                m.set_at(i, j+1, -mult * c_j);
+/// Writes the program in the format read by `Parser::parse_lp`.
+impl Display for LinearProgram {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.goal {
+            Maximize => writeln!(f, "MAXIMIZE")?,
+            Minimize => writeln!(f, "MINIMIZE")?,
+        }
+        // The parser needs a variable in the objective
+        if let (true, Some(x)) = (self.obj.is_empty(), self.vars.first()) {
+            write!(f, "0 {}", x)?;
+        }
+        for (k, &(c, ref x)) in self.obj.iter().enumerate() {
+            if k == 0 {
+                write!(f, "{} {}", c, x)?;
+            } else if c < 0.0 {
+                write!(f, " - {} {}", -c, x)?;
+            } else {
+                write!(f, " + {} {}", c, x)?;
             }
         }
-
-        let mut obj: Vec<f64> = init_zero_vec(self.vars.len() + 1, 0.0);
-        let mult: f64 = match self.goal {
-            Maximize => 1.0,
-            Minimize => -1.0,
-        };
-        obj[0] = self.obj_cst;
-        for &(c_j, ref x_j) in self.obj.iter() {
-            obj[self.var_idx(&x_j) + 1] = mult * c_j;
+        writeln!(f, "\n\nSUBJECT TO\n")?;
+        for ineq in self.ineqs.iter() {
+            writeln!(f, "{}", ineq)?;
         }
-
-        let mut lc: Vec<usize> = init_zero_vec(m.w, 0);
-        let mut i = 0;
-        for label in lc.iter_mut() {
-            *label = i;
-            i += 1;
+        writeln!(f, "\nBOUNDS\n")?;
+        for b in self.bounds.iter() {
+            writeln!(f, "{}", b)?;
         }
-
-        let mut ll: Vec<usize> = init_zero_vec(m.h, 0);
-        for label in ll.iter_mut() {
-            *label = i;
-            i += 1;
+        if !self.integers.is_empty() {
+            writeln!(f, "\nGENERAL\n")?;
+            for x in self.integers.iter() {
+                writeln!(f, "{}", x)?;
+            }
         }
-
-        let mw = m.w;
-        Dictionary {
-            m: m,
-            ll: ll,
-            lc: lc,
-            obj: obj,
-            weq: init_zero_vec(mw, 0.0),
-            var_name: "x",
+        writeln!(f, "\nVARIABLES\n")?;
+        for x in self.vars.iter() {
+            writeln!(f, "{}", x)?;
         }
+        Ok(())
     }
+}
 
-    fn normalize_bounds(&mut self) {
-        let bounds = self.bounds.clone();
-        for (i, _) in bounds.iter().enumerate() {
-            self.handle_upper_bound(i);
-            self.handle_lower_bound(i);
+impl LinearProgram {
+    pub fn new(goal: ObjectiveKind, obj: Vec<(f64, String)>, ineqs: Vec<PInequation<f64>>,
+               bounds: Vec<PBound>, integers: Vec<String>, vars: Vec<String>) -> LinearProgram {
+        let vars_inv = LinearProgram::build_vars_inv(&vars);
+        LinearProgram {
+            obj,
+            obj_cst: 0.0,
+            goal,
+            ineqs,
+            bounds,
+            integers,
+            vars,
+            vars_inv,
         }
     }
 
-    fn handle_upper_bound(&mut self, bidx: usize) {
-        let b = self.bounds[bidx].clone();
-        if let Some(u) = b.upper {
-            self.ineqs.push(PInequation {
-                prods: vec![(1.0, b.var)],
-                kind: OrderRel::LT,
-                cst: u,
-            });
-            self.bounds[bidx].upper = None;
-        }
+    /// The dictionary of the standard form of the program, in which each
+    /// variable is shifted by its lower bound (see `standard_form`).
+    pub fn to_dict(&self) -> Result<Dictionary<f64>, Error> {
+        Ok(self.to_normal()?.to_dict())
     }
 
-    // The bound has no upper bound
-    fn handle_lower_bound(&mut self, bidx: usize) {
-        let b = self.bounds[bidx].clone();
-        match b {
-            PBound { var: ref x_j, upper: None, lower: Some(lower) } => {
-                if lower == 0.0 { return; }
-                self.translate_var(&x_j, lower);
-                let nvar = self.create_dummy_var(x_j, "tr");
-                self.bounds[bidx] = PBound {
-                    var: nvar,
-                    upper: None,
-                    lower: Some(0.0),
-                };
-            },
-            _ => panic!("Problem while handling lower bounds"),
-        }
-
+    pub fn to_normal(&self) -> Result<NormalLinearProgram<f64>, Error> {
+        standard_form(self).map(|sf| sf.lp)
     }
 
-    fn translate_var(&mut self, x_j: &str, t: f64) {
-        for ineq in self.ineqs.iter_mut() {
-            let j = match ineq.prods.iter().position(|x| x.1 == x_j) {
-                Some(j) => j,
-                None => continue,
-            };
-            let k = ineq.prods[j].0;
-            ineq.cst += k * t;
-        }
-        match self.obj.iter().position(|x| x.1 == x_j) {
-            Some(j) => {
-                self.obj_cst -= t * self.obj[j].0;
-            },
-            None => (),
-        }
+    /// The kind of each line of the dictionary given by `to_dict`: the
+    /// upper bounds come after the constraints and the equalities are split.
+    pub fn row_kinds(&self) -> Result<Vec<OrderRel>, Error> {
+        standard_form(self).map(|sf| sf.rows.iter().map(|r| r.kind()).collect())
     }
 
-    // TODO: record the relationships
-    fn create_dummy_var(&mut self, var: &str, msg: &str) -> String {
-        let res = format!("{}${}${}", var, self.dummy_idx, msg).to_string();
-        self.dummy_idx += 1;
-        res
+    /// Labels of the integer variables in the dictionary given by `to_dict`
+    pub fn integer_labels(&self) -> Result<Vec<usize>, Error> {
+        self.integers.iter().map(|x| self.var_idx(x).map(|j| j + 1)).collect()
     }
 
-    #[inline(never)]
-    fn var_idx(&self, var: &str) -> usize {
-        if let Some(&res) = self.vars_inv.get(var) {
-            return res;
-        }
-        panic!("Couldn't find variable {}", var);
+    fn var_idx(&self, var: &str) -> Result<usize, Error> {
+        self.vars_inv.get(var).cloned().ok_or_else(|| Error::Model(format!("unknown variable {}", var)))
     }
 
     fn build_vars_inv(vars: &Vec<String>) -> HashMap<String, usize> {
@@ -170,6 +169,22 @@ impl LinearProgram {
 
 }
 
+/// Counts the lines up to increasing offsets of a source, without going
+/// through it again from the start.
+struct LineCounter<'a> {
+    src: &'a str,
+    offset: usize,
+    line: usize,
+}
+
+impl<'a> LineCounter<'a> {
+    fn at(&mut self, offset: usize) -> usize {
+        self.line += self.src[self.offset..offset].matches('\n').count();
+        self.offset = offset;
+        self.line
+    }
+}
+
 #[derive(Clone)]
 pub struct Parser<'a> {
     src: &'a str,
@@ -185,43 +200,105 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn parse_lp(src: &str) -> LinearProgram {
+    pub fn parse_lp(src: &str) -> Result<LinearProgram, Error> {
+        Parser::parse_lp_with_locations(src).map(|(lp, _)| lp)
+    }
+
+    /// Parses a program along with the lines where its parts are written.
+    pub fn parse_lp_with_locations(src: &str) -> Result<(LinearProgram, Locations), Error> {
         let mut p = Parser::new(src);
+        let mut lines = LineCounter { src, offset: 0, line: 1 };
+        let mut loc = Locations::default();
         let mut ineqs: Vec<PInequation<f64>> = Vec::new();
         let mut bounds: Vec<PBound> = Vec::new();
         p.ws();
-        let goal = p.obj_kind();
+        let goal = p.obj_kind()?;
+        p.ws();
+        loc.obj = lines.at(p.offset());
         let obj = p.sum();
         p.ws();
         p.word(); p.ws(); p.word(); // TODO
         p.ws();
-        while let Some(eq) = p.inequation() {
-            ineqs.push(eq);
+        loop {
+            let offset = p.offset();
+            match p.inequation() {
+                Some(eq) => ineqs.push(eq),
+                None => break,
+            }
+            loc.ineqs.push(lines.at(offset));
             p.ws();
         }
-        let r = p.word();
-        assert!(r == "BOUNDS");
+        p.keyword("BOUNDS")?;
         p.ws();
-        while let Some(b) = p.bound() {
-            bounds.push(b);
+        loop {
+            let offset = p.offset();
+            match p.bound()? {
+                Some(b) => bounds.push(b),
+                None => break,
+            }
+            loc.bounds.push(lines.at(offset));
+            p.ws();
+        }
+        let mut integers: Vec<String> = Vec::new();
+        loop {
+            let backup = p.clone();
+            let r = p.word();
+            if r != "GENERAL" && r != "BINARY" {
+                p = backup;
+                break;
+            }
             p.ws();
+            for (x, offset) in p.section_variables() {
+                let line = lines.at(offset);
+                if r == "BINARY" {
+                    bounds.push(PBound {
+                        var: x.clone(),
+                        upper: Some(1.0),
+                        lower: Some(0.0),
+                    });
+                    loc.bounds.push(line);
+                }
+                integers.push(x);
+                loc.integers.push(line);
+            }
         }
-        let r = p.word();
-        assert_eq!(r, "VARIABLES");
+        p.keyword("VARIABLES")?;
         p.ws();
-        let vars = p.variables();
-        let vars_inv = LinearProgram::build_vars_inv(&vars);
+        let mut vars = Vec::new();
+        for (x, offset) in p.variables() {
+            vars.push(x);
+            loc.vars.push(lines.at(offset));
+        }
 
-        LinearProgram {
-           obj: obj,
-           obj_cst: 0.0,
-           goal: goal,
-           ineqs: ineqs,
-           bounds: bounds,
-           vars: vars,
-           vars_inv: vars_inv,
+        Ok((LinearProgram::new(goal, obj, ineqs, bounds, integers, vars), loc))
+    }
+
+    /// Offset of the next character in the source
+    fn offset(&self) -> usize {
+        self.peek(0).map_or(self.src.len(), |(i, _)| i)
+    }
+
+    /// Line of the next character, starting at 1
+    fn line(&self) -> usize {
+        self.src[..self.offset()].matches('\n').count() + 1
+    }
 
-           dummy_idx: 0,
+    /// Error on the next characters, which are not `expected`
+    fn unexpected(&self, expected: &str) -> Error {
+        let found = match self.src[self.offset()..].lines().next() {
+            Some(rest) if !rest.trim().is_empty() => format!("`{}`", rest.trim()),
+            _ => "the end of the line".to_string(),
+        };
+        Error::Parse(self.line(), format!("expected {}, found {}", expected, found))
+    }
+
+    /// Parse the keyword `kw`
+    fn keyword(&mut self, kw: &str) -> Result<(), Error> {
+        let backup = self.clone();
+        if self.word() == kw {
+            Ok(())
+        } else {
+            Err(backup.unexpected(kw))
         }
     }
 
@@ -259,27 +336,43 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Parse the VARIABLE clause
-    fn variables(&mut self) -> Vec<String> {
-        let mut res: Vec<String> = Vec::new();
+    /// Parse the VARIABLE clause, with the offset of each variable
+    fn variables(&mut self) -> Vec<(String, usize)> {
+        let mut res: Vec<(String, usize)> = Vec::new();
         loop {
+            let offset = self.offset();
             let v = self.word();
             if v == "" { break; }
-            res.push(v);
+            res.push((v, offset));
+            self.ws();
+        }
+        res
+    }
+
+    /// Parse the variables of a GENERAL or BINARY clause, with their offsets
+    fn section_variables(&mut self) -> Vec<(String, usize)> {
+        let mut res: Vec<(String, usize)> = Vec::new();
+        loop {
+            let backup = self.clone();
+            let offset = self.offset();
+            let v = self.word();
+            if v.is_empty() || v == "GENERAL" || v == "BINARY" || v == "VARIABLES" {
+                *self = backup;
+                break;
+            }
+            res.push((v, offset));
             self.ws();
         }
         res
     }
 
     /// Parse the kind of objective
-    fn obj_kind(&mut self) -> ObjectiveKind {
-        let k = self.word();
-        if k == "MINIMIZE" {
-            Minimize
-        } else if k == "MAXIMIZE" {
-            Maximize
-        } else {
-            panic!("The file must begin with MAXIMIZE or MINIMIZE");
+    fn obj_kind(&mut self) -> Result<ObjectiveKind, Error> {
+        let backup = self.clone();
+        match &self.word()[..] {
+            "MINIMIZE" => Ok(Minimize),
+            "MAXIMIZE" => Ok(Maximize),
+            _ => Err(backup.unexpected("MAXIMIZE or MINIMIZE")),
         }
     }
 
@@ -366,23 +459,26 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn bound(&mut self) -> Option<PBound> {
+    fn bound(&mut self) -> Result<Option<PBound>, Error> {
         match self.peek(0) {
             Some((_, c)) if Parser::is_number_start(c) =>
                 self.double_bound(),
             Some(_) =>
-                self.single_bound(),
-            None => None,
+                Ok(self.single_bound()),
+            None => Ok(None),
         }
     }
 
-    fn double_bound(&mut self) -> Option<PBound> {
+    /// Parse `l <= x <= u`
+    fn double_bound(&mut self) -> Result<Option<PBound>, Error> {
+        let backup = self.clone();
         let lb = self.signed_number();
         self.ws();
         if self.cmp_op() != Some(OrderRel::LT) {
-            panic!("Parsing error: Bound the wrong way arroung: must be 1729 >= X >= 20015"); }
+            return Err(backup.unexpected("a bound `l <= x <= u`"));
+        }
         self.ws();
-        match lb {
+        Ok(match lb {
             None => None,
             Some(_) => {
                 self.single_bound().map(|mut b| {
@@ -390,7 +486,7 @@ impl<'a> Parser<'a> {
                     b
                 })
             },
-        }
+        })
     }
 
     fn single_bound(&mut self) -> Option<PBound> {
@@ -437,16 +533,12 @@ impl<'a> Parser<'a> {
         self.number()
     }
 
+    /// Parse a number, `None` if it is not valid as `1.2.3`
     fn number(&mut self) -> Option<f64> {
-        let mut seen_dot = false;
         let mut res = String::new();
         loop {
             match self.peek(0) {
                 Some((_, c)) if Parser::is_number_start(c) => {
-                    if c == '.' {
-                        if seen_dot { panic!("Error while parsing number {}", res); }
-                        seen_dot = true;
-                    }
                     res.push(c);
                     self.cur.next();
                 },
@@ -461,7 +553,6 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use linear_system::*;
 
     #[test]
     fn test_word() {
@@ -476,7 +567,7 @@ mod test {
     #[test]
     fn test_begining() {
         let mut p = Parser::new("MINIMIZE t_1 + ...");
-        assert_eq!(p.obj_kind(), Minimize);
+        assert_eq!(p.obj_kind().unwrap(), Minimize);
     }
 
     #[test]
@@ -517,6 +608,51 @@ mod test {
         assert_eq!(p.inequation(), Some(expected));
     }
 
+    #[test]
+    fn test_integers() {
+        let lp = Parser::parse_lp("MAXIMIZE x + y\nSUBJECT TO\nx + y <= 3.5\nBOUNDS\n\
+                                   GENERAL\nx\nBINARY\ny\nVARIABLES\nx\ny\n").unwrap();
+        assert_eq!(lp.integers, vec!["x".to_string(), "y".to_string()]);
+        assert_eq!(lp.integer_labels().unwrap(), vec![1, 2]);
+        assert_eq!(lp.bounds, vec![PBound { var: "y".to_string(), upper: Some(1.0), lower: Some(0.0) }]);
+    }
+
+    #[test]
+    fn test_display() {
+        let lp = Parser::parse_lp("MINIMIZE\n-0.5 x + 3 y - z\nSUBJECT TO\nx - 2 y >= -4\n\
+                                   x + y + z = 10.5\nBOUNDS\n1 <= x <= 4\nz >= 2\nGENERAL\ny\n\
+                                   VARIABLES\nx\ny\nz\n").unwrap();
+        assert_eq!(Parser::parse_lp(&lp.to_string()).unwrap(), lp);
+    }
+
+    #[test]
+    fn test_locations() {
+        let (lp, loc) = Parser::parse_lp_with_locations("MAXIMIZE\nx + y\nSUBJECT TO\nx + y <= 3\n\n\
+                                                         x - y >= 1\nBOUNDS\nx <= 2\nBINARY\ny\n\
+                                                         VARIABLES\nx\ny\n").unwrap();
+        assert_eq!(lp.bounds.len(), 2);
+        assert_eq!(loc, Locations {
+            obj: 2,
+            ineqs: vec![4, 6],
+            bounds: vec![8, 10],
+            integers: vec![10],
+            vars: vec![12, 13],
+        });
+    }
+
+    #[test]
+    fn test_display_empty() {
+        let ineqs = vec![PInequation { prods: vec![(1.0, "x".to_string()), (-1.0, "y".to_string())],
+                                       kind: OrderRel::LT, cst: 0.0 }];
+        let bounds = vec![PBound { var: "x".to_string(), upper: None, lower: None }];
+        let vars = vec!["x".to_string(), "y".to_string()];
+        let lp = LinearProgram::new(Maximize, Vec::new(), ineqs, bounds, Vec::new(), vars);
+        let read = Parser::parse_lp(&lp.to_string()).unwrap();
+        assert_eq!(read.obj, vec![(0.0, "x".to_string())]);
+        assert_eq!((&read.ineqs, &read.vars), (&lp.ineqs, &lp.vars));
+        assert_eq!(read.bounds, vec![PBound { var: "x".to_string(), upper: None, lower: Some(0.0) }]);
+    }
+
     #[test]
     fn test_bounds() {
         let mut p = Parser::new("33.3 <= x <= 99");
@@ -525,6 +661,19 @@ mod test {
             upper: Some(99.0),
             lower: Some(33.3),
         };
-        assert_eq!(p.bound(), Some(expected));
+        assert_eq!(p.bound().unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn test_lower_bounds() {
+        // x = 3 and y = 2 at the optimum, shifted by 3 and -1 in the dictionary
+        let lp = Parser::parse_lp("MAXIMIZE x + 2 y\nSUBJECT TO\nx + y <= 5\nBOUNDS\n3 <= x <= 4\n\
+                                   y >= -1\nVARIABLES\nx\ny\n").unwrap();
+        let mut d = lp.to_dict().unwrap();
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(7.0));
+        assert_eq!(lp.row_kinds().unwrap(), vec![OrderRel::LT, OrderRel::LT]);
+        let values = d.values();
+        assert_eq!((values[1], values[2]), (0.0, 3.0));
     }
 }
+
@@ -0,0 +1,487 @@
+//! Presolve: simplifications of a `LinearProgram` before it is turned into a
+//! dictionary, and the postsolve which gives back the values of the variables
+//! of the original program.
+//!
+//! The following reductions are applied until none of them applies anymore:
+//!
+//! - rows without coefficients are checked and removed;
+//! - singleton rows `a x (rel) b` become bounds on `x`;
+//! - variables whose bounds are equal are fixed and substituted;
+//! - variables which appear in no row are fixed at their best bound;
+//! - duplicate rows (up to a factor) are merged into the tightest one.
+//!
+//! As in `to_dict`, the variables are non-negative unless a lower bound says
+//! otherwise.
+
+use std::collections::HashMap;
+use std::f64;
+
+use linear_system::*;
+use branch_bound::INT_TOL;
+use parser::{LinearProgram, PInequation, PBound};
+use error::Error;
+
+const PRESOLVE_TOL: f64 = 1e-9;
+
+struct Row {
+    index: usize, // in the original program
+    coeffs: Vec<(usize, f64)>, // sorted by variable, without zeros
+    kind: OrderRel,
+    cst: f64,
+}
+
+/// What is needed to recover the solution of the original program from the
+/// one of the presolved program.
+#[derive(Clone, Debug)]
+pub struct Postsolve {
+    pub rows_removed: usize,
+    pub cols_removed: usize,
+    fixed: Vec<Option<f64>>, // values of the removed variables
+    kept: Vec<usize>, // index in the original program of each remaining variable
+    kept_rows: Vec<usize>, // index in the original program of each remaining row
+}
+
+impl Postsolve {
+    /// Values of all the variables of the original program, labelled as in its
+    /// dictionary, from the values of the presolved dictionary.
+    pub fn postsolve(&self, values: &[(usize, f64)]) -> Vec<(usize, f64)> {
+        let mut res: Vec<f64> = self.fixed.iter().map(|v| v.unwrap_or(0.0)).collect();
+        for &(label, v) in values.iter() {
+            if label >= 1 && label <= self.kept.len() {
+                res[self.kept[label - 1]] = v;
+            }
+        }
+        res.into_iter().enumerate().map(|(j, v)| (j + 1, v)).collect()
+    }
+
+    /// The dual values of the rows of the original program `lp` and the
+    /// reduced costs of its variables, from the dual values of the rows of
+    /// the presolved program given by `StandardForm::marginals`. The removed
+    /// rows have a nil dual value, and all the reduced costs are computed
+    /// again from the objective of `lp`, so that the bounds which replaced
+    /// rows show there.
+    pub fn postsolve_marginals(&self, lp: &LinearProgram, duals: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let mut res = init_zero_vec(lp.ineqs.len(), 0.0);
+        for (&k, &y) in self.kept_rows.iter().zip(duals.iter()) {
+            res[k] = y;
+        }
+        let idx = |x: &str| lp.vars.iter().position(|y| y == x);
+        let mut reduced = init_zero_vec(lp.vars.len(), 0.0);
+        for &(c, ref x) in lp.obj.iter() {
+            if let Some(j) = idx(x) {
+                reduced[j] += c;
+            }
+        }
+        for (ineq, &y) in lp.ineqs.iter().zip(res.iter()) {
+            for &(a, ref x) in ineq.prods.iter() {
+                if let Some(j) = idx(x) {
+                    reduced[j] -= y * a;
+                }
+            }
+        }
+        (res, reduced)
+    }
+}
+
+struct Presolver<'a> {
+    lp: &'a LinearProgram,
+    rows: Vec<Option<Row>>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    obj: Vec<f64>, // in the maximization sense, like the dictionary
+    obj_cst: f64,
+    integer: Vec<bool>,
+    fixed: Vec<Option<f64>>,
+}
+
+fn flip(kind: OrderRel) -> OrderRel {
+    match kind {
+        OrderRel::LT => OrderRel::GT,
+        OrderRel::GT => OrderRel::LT,
+        OrderRel::EQ => OrderRel::EQ,
+    }
+}
+
+impl<'a> Presolver<'a> {
+    fn new(lp: &'a LinearProgram) -> Result<Presolver<'a>, Error> {
+        let n = lp.vars.len();
+        let idx: HashMap<&str, usize> = lp.vars.iter().enumerate().map(|(j, x)| (&x[..], j)).collect();
+        let find = |x: &str| idx.get(x).cloned().ok_or_else(|| Error::Model(format!("unknown variable {}", x)));
+
+        let mut rows = Vec::new();
+        for (index, ineq) in lp.ineqs.iter().enumerate() {
+            let mut dense: HashMap<usize, f64> = HashMap::new();
+            for &(a, ref x) in ineq.prods.iter() {
+                *dense.entry(find(x)?).or_insert(0.0) += a;
+            }
+            let mut coeffs: Vec<(usize, f64)> = dense.into_iter().filter(|&(_, a)| a != 0.0).collect();
+            coeffs.sort_by_key(|&(j, _)| j);
+            rows.push(Some(Row { index, coeffs, kind: ineq.kind, cst: ineq.cst }));
+        }
+
+        let mut integer = init_zero_vec(n, false);
+        for x in lp.integers.iter() {
+            integer[find(x)?] = true;
+        }
+
+        // As in `standard_form`, the last lower bound and the smallest upper
+        // bound are kept, and those of integer variables are rounded inward
+        let mut lower: Vec<f64> = init_zero_vec(n, 0.0);
+        let mut upper = init_zero_vec(n, f64::INFINITY);
+        for b in lp.bounds.iter() {
+            let j = find(&b.var)?;
+            if let Some(l) = b.lower {
+                lower[j] = l;
+            }
+            if let Some(u) = b.upper {
+                upper[j] = upper[j].min(u);
+            }
+        }
+        for j in (0..n).filter(|&j| integer[j]) {
+            lower[j] = (lower[j] - INT_TOL).ceil();
+            upper[j] = (upper[j] + INT_TOL).floor();
+        }
+
+        let mult = match lp.goal {
+            Maximize => 1.0,
+            Minimize => -1.0,
+        };
+        let mut obj: Vec<f64> = init_zero_vec(n, 0.0);
+        for &(c, ref x) in lp.obj.iter() {
+            obj[find(x)?] += mult * c;
+        }
+
+        Ok(Presolver {
+            lp,
+            rows,
+            lower,
+            upper,
+            obj,
+            obj_cst: lp.obj_cst,
+            integer,
+            fixed: init_zero_vec(n, None),
+        })
+    }
+
+    fn tighten(&mut self, j: usize, kind: OrderRel, v: f64) {
+        let (lo, up) = if self.integer[j] {
+            ((v - PRESOLVE_TOL).ceil(), (v + PRESOLVE_TOL).floor())
+        } else {
+            (v, v)
+        };
+        if kind != OrderRel::LT {
+            self.lower[j] = self.lower[j].max(lo);
+        }
+        if kind != OrderRel::GT {
+            self.upper[j] = self.upper[j].min(up);
+        }
+    }
+
+    fn fix(&mut self, j: usize, v: f64) {
+        self.fixed[j] = Some(v);
+        for row in self.rows.iter_mut().flatten() {
+            if let Some(k) = row.coeffs.iter().position(|&(l, _)| l == j) {
+                row.cst -= row.coeffs[k].1 * v;
+                row.coeffs.remove(k);
+            }
+        }
+        self.obj_cst += self.obj[j] * v;
+        self.obj[j] = 0.0;
+    }
+
+    fn check_bounds(&self) -> Result<(), String> {
+        for j in 0..self.lp.vars.len() {
+            if self.fixed[j].is_none() && self.lower[j] > self.upper[j] + PRESOLVE_TOL {
+                return Err(format!("the bounds of {} are contradictory", self.lp.vars[j]));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the empty and singleton rows.
+    fn small_rows(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        for i in 0..self.rows.len() {
+            let (coeffs, kind, cst) = match self.rows[i] {
+                Some(ref row) if row.coeffs.len() <= 1 => (row.coeffs.clone(), row.kind, row.cst),
+                _ => continue,
+            };
+            match coeffs.first() {
+                None => {
+                    let ok = match kind {
+                        OrderRel::LT => cst >= -PRESOLVE_TOL,
+                        OrderRel::GT => cst <= PRESOLVE_TOL,
+                        OrderRel::EQ => cst.abs() <= PRESOLVE_TOL,
+                    };
+                    if !ok {
+                        return Err(format!("row {} cannot be satisfied", i + 1));
+                    }
+                },
+                Some(&(j, a)) => {
+                    let kind = if a < 0.0 { flip(kind) } else { kind };
+                    self.tighten(j, kind, cst / a);
+                },
+            }
+            self.rows[i] = None;
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    fn fixed_columns(&mut self) -> bool {
+        let mut changed = false;
+        for j in 0..self.lp.vars.len() {
+            if self.fixed[j].is_none() && (self.upper[j] - self.lower[j]).abs() <= PRESOLVE_TOL {
+                let v = self.lower[j];
+                self.fix(j, v);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Fixes the variables which appear in no row at their best bound.
+    fn empty_columns(&mut self) -> bool {
+        let mut used = init_zero_vec(self.lp.vars.len(), false);
+        for row in self.rows.iter().flatten() {
+            for &(j, _) in row.coeffs.iter() {
+                used[j] = true;
+            }
+        }
+        let mut changed = false;
+        for (j, &used) in used.iter().enumerate() {
+            if used || self.fixed[j].is_some() { continue; }
+            let v = if self.obj[j] > PRESOLVE_TOL { self.upper[j] } else { self.lower[j] };
+            if v.is_finite() { // otherwise the program is unbounded if it is feasible
+                self.fix(j, v);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Ratio `r` such that the coefficients of `b` are `r` times those of `a`.
+    fn ratio(a: &Row, b: &Row) -> Option<f64> {
+        if a.coeffs.len() != b.coeffs.len() || a.coeffs.is_empty() {
+            return None;
+        }
+        let r = b.coeffs[0].1 / a.coeffs[0].1;
+        let prop = a.coeffs.iter().zip(b.coeffs.iter()).all(|(&(ja, ca), &(jb, cb))| {
+            ja == jb && (cb - r * ca).abs() <= PRESOLVE_TOL * cb.abs().max(1.0)
+        });
+        if prop { Some(r) } else { None }
+    }
+
+    /// Merges the rows which are multiples of one another and of the same kind,
+    /// keeping the tightest one.
+    fn duplicate_rows(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        for i in 0..self.rows.len() {
+            for k in i+1..self.rows.len() {
+                let (r, kind, cst) = match (&self.rows[i], &self.rows[k]) {
+                    (Some(a), Some(b)) => match Presolver::ratio(a, b) {
+                        Some(r) => (r, if r < 0.0 { flip(b.kind) } else { b.kind }, b.cst / r),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                let row = self.rows[i].as_ref().unwrap();
+                if kind != row.kind { continue; }
+                let tighter = match kind {
+                    OrderRel::LT => cst < row.cst,
+                    OrderRel::GT => cst > row.cst,
+                    OrderRel::EQ if (row.cst - cst).abs() > PRESOLVE_TOL * r.abs().max(1.0) => {
+                        return Err(format!("rows {} and {} cannot both be satisfied", i + 1, k + 1));
+                    },
+                    OrderRel::EQ => false,
+                };
+                if tighter {
+                    self.rows[i] = self.rows[k].take();
+                } else {
+                    self.rows[k] = None;
+                }
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    fn run(&mut self) -> Result<(), String> {
+        loop {
+            let mut changed = self.small_rows()?;
+            self.check_bounds()?;
+            changed |= self.fixed_columns();
+            changed |= self.empty_columns();
+            changed |= self.duplicate_rows()?;
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    fn reduced(&self) -> (LinearProgram, Postsolve) {
+        let vars = &self.lp.vars;
+        let kept: Vec<usize> = (0..vars.len()).filter(|&j| self.fixed[j].is_none()).collect();
+        let mut ineqs: Vec<PInequation<f64>> = self.rows.iter().flatten().map(|row| PInequation {
+            prods: row.coeffs.iter().map(|&(j, a)| (a, vars[j].clone())).collect(),
+            kind: row.kind,
+            cst: row.cst,
+        }).collect();
+        let kept_rows = self.rows.iter().flatten().map(|row| row.index).collect();
+        let rows_removed = self.lp.ineqs.len() - ineqs.len();
+
+        let mut bounds = Vec::new();
+        for &j in kept.iter() {
+            // Positive lower bounds are kept as rows so that `to_dict` does not shift the variables
+            if self.lower[j] > 0.0 {
+                ineqs.push(PInequation {
+                    prods: vec![(1.0, vars[j].clone())],
+                    kind: OrderRel::GT,
+                    cst: self.lower[j],
+                });
+            }
+            if self.upper[j].is_finite() || self.lower[j] < 0.0 {
+                bounds.push(PBound {
+                    var: vars[j].clone(),
+                    upper: if self.upper[j].is_finite() { Some(self.upper[j]) } else { None },
+                    lower: Some(self.lower[j].min(0.0)),
+                });
+            }
+        }
+
+        let mult = match self.lp.goal {
+            Maximize => 1.0,
+            Minimize => -1.0,
+        };
+        let obj = kept.iter()
+            .filter(|&&j| self.obj[j] != 0.0)
+            .map(|&j| (mult * self.obj[j], vars[j].clone()))
+            .collect();
+        let integers = kept.iter().filter(|&&j| self.integer[j]).map(|&j| vars[j].clone()).collect();
+
+        let mut lp = LinearProgram::new(self.lp.goal, obj, ineqs, bounds, integers,
+                                        kept.iter().map(|&j| vars[j].clone()).collect());
+        lp.obj_cst = self.obj_cst;
+        let post = Postsolve {
+            rows_removed,
+            cols_removed: vars.len() - kept.len(),
+            fixed: self.fixed.clone(),
+            kept,
+            kept_rows,
+        };
+        (lp, post)
+    }
+}
+
+/// The outcome of the presolve, which may find that the program is
+/// infeasible without solving it.
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)] // built once per program
+pub enum Presolved {
+    Reduced(LinearProgram, Postsolve),
+    Infeasible(String), // why
+}
+
+/// Simplifies `lp`, or tells why it is infeasible.
+pub fn presolve(lp: &LinearProgram) -> Result<Presolved, Error> {
+    let mut p = Presolver::new(lp)?;
+    Ok(match p.run() {
+        Ok(()) => {
+            let (reduced, post) = p.reduced();
+            Presolved::Reduced(reduced, post)
+        },
+        Err(why) => Presolved::Infeasible(why),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+    use standard_form::standard_form;
+
+    fn reduce(lp: &LinearProgram) -> (LinearProgram, Postsolve) {
+        match presolve(lp).unwrap() {
+            Presolved::Reduced(reduced, post) => (reduced, post),
+            Presolved::Infeasible(why) => panic!("infeasible: {}", why),
+        }
+    }
+
+    fn infeasible(lp: &LinearProgram) -> bool {
+        matches!(presolve(lp).unwrap(), Presolved::Infeasible(_))
+    }
+
+    #[test]
+    fn test_reductions() {
+        // z is fixed, y only has a bound, w is unused and the last row is twice the third one
+        let lp = Parser::parse_lp("MAXIMIZE x + 2 y + z\nSUBJECT TO\nx + y + z <= 10\n\
+                                   2 y <= 6\nx + y <= 5\n2 x + 2 y <= 12\n\
+                                   BOUNDS\n3 <= z <= 3\nVARIABLES\nx\ny\nz\nw\n").unwrap();
+        let (reduced, post) = reduce(&lp);
+        assert_eq!(reduced.vars, vec!["x".to_string(), "y".to_string()]);
+        assert_eq!(reduced.ineqs.len(), 1);
+        assert_eq!((post.rows_removed, post.cols_removed), (3, 2));
+
+        let mut d = reduced.to_dict().unwrap();
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(11.0));
+        let values: Vec<(usize, f64)> = (0..d.h()).map(|i| (d.ll[i], d.m.at(i, 0))).collect();
+        assert_eq!(post.postsolve(&values), vec![(1, 2.0), (2, 3.0), (3, 3.0), (4, 0.0)]);
+    }
+
+    #[test]
+    fn test_marginals() {
+        // 2 y <= 6 becomes a bound, which shows in the reduced cost of y, and
+        // the first row is merged into the tighter last one once z is fixed
+        let lp = Parser::parse_lp("MAXIMIZE x + 2 y + z\nSUBJECT TO\nx + y + z <= 10\n2 y <= 6\nx + y <= 5\n\
+                                   BOUNDS\n3 <= z <= 3\nVARIABLES\nx\ny\nz\nw\n").unwrap();
+        let (reduced, post) = reduce(&lp);
+        let sf = standard_form(&reduced).unwrap();
+        let mut d = sf.lp.to_dict();
+        d.run_simplex(Heuristic::Bland, false);
+        let (duals, _) = sf.marginals(reduced.ineqs.len(), &d);
+        let (duals, costs) = post.postsolve_marginals(&lp, &duals);
+        assert_eq!(duals, vec![0.0, 0.0, 1.0]);
+        assert_eq!(costs, vec![0.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_minimize() {
+        let lp = Parser::parse_lp("MINIMIZE x + y + 2 z\nSUBJECT TO\nx + y + z >= 2\nx - z <= 1\n\
+                                   BOUNDS\n1 <= y <= 1\nVARIABLES\nx\ny\nz\n").unwrap();
+        let (reduced, post) = reduce(&lp);
+        assert_eq!(post.cols_removed, 1);
+        let mut d = reduced.to_dict().unwrap();
+        // minimizing x + y + 2 z is maximizing -x - 2 z - 1
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(-2.0));
+    }
+
+    #[test]
+    fn test_infeasible() {
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx >= 3\n2 x <= 4\nBOUNDS\nVARIABLES\nx\n").unwrap();
+        assert!(infeasible(&lp));
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\n0 x >= 1\nBOUNDS\nVARIABLES\nx\n").unwrap();
+        assert!(infeasible(&lp));
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\n2 x <= 3\nx + y <= 5\nBOUNDS\nGENERAL\nx\n\
+                                   VARIABLES\nx\ny\n").unwrap();
+        let (reduced, _) = reduce(&lp);
+        assert_eq!(reduced.bounds[0].upper, Some(1.0));
+    }
+
+    #[test]
+    fn test_integer_bounds() {
+        // x is unused, so it is fixed at its largest integer value
+        let lp = Parser::parse_lp("MAXIMIZE x + y\nSUBJECT TO\ny <= 2\ny + z <= 4\nBOUNDS\n\
+                                   0.5 <= x <= 3.7\nGENERAL\nx\nVARIABLES\nx\ny\nz\n").unwrap();
+        let (reduced, post) = reduce(&lp);
+        let mut d = reduced.to_dict().unwrap();
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(5.0));
+        assert_eq!(post.postsolve(&[])[0], (1, 3.0));
+
+        // the last lower bound is kept, as in `standard_form`
+        let lp = Parser::parse_lp("MINIMIZE x + y\nSUBJECT TO\nx + y >= 0\nBOUNDS\nx >= 2\nx >= 1\n\
+                                   VARIABLES\nx\ny\n").unwrap();
+        let (reduced, _) = reduce(&lp);
+        let mut d = reduced.to_dict().unwrap();
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(-1.0));
+    }
+}
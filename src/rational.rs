@@ -0,0 +1,158 @@
+//! Exact rationals on 64-bit integers, for the cutting planes.
+//!
+//! Numerators and denominators grow with every cut, so each operation checks
+//! for overflows. An overflowed value behaves like a NaN: it is unordered, it
+//! is not equal to itself and every operation on it overflows too, so the
+//! computation goes on and the overflow is detected once it is finished (see
+//! `is_overflow`).
+
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Sub, Mul, Div, Rem};
+
+use num::{Num, Zero, One, Integer};
+use num::rational::{Ratio, ParseRatioError};
+use num::traits::{CheckedAdd, CheckedSub, CheckedMul, CheckedDiv};
+
+use linear_system::Tolerance;
+use gomory::Floor;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rational(Option<Ratio<i64>>); // None after an overflow
+
+impl Rational {
+    pub fn new(numer: i64, denom: i64) -> Rational {
+        Rational(Some(Ratio::new(numer, denom)))
+    }
+
+    pub fn from_integer(n: i64) -> Rational {
+        Rational(Some(Ratio::from_integer(n)))
+    }
+
+    /// The closest rational to `x` with small terms, if there is one.
+    pub fn approximate_float(x: f64) -> Option<Rational> {
+        Ratio::approximate_float(x).map(|r| Rational(Some(r)))
+    }
+
+    /// Whether an operation giving this value has overflowed.
+    pub fn is_overflow(&self) -> bool {
+        self.0.is_none()
+    }
+
+    fn checked<O>(self, other: Rational, op: O) -> Rational
+        where O: Fn(&Ratio<i64>, &Ratio<i64>) -> Option<Ratio<i64>> {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => Rational(op(&a, &b)),
+            _ => Rational(None),
+        }
+    }
+}
+
+/// Whether `x` is unordered, as an overflowed `Rational` or a NaN.
+pub fn is_unordered<F: PartialOrd>(x: F) -> bool {
+    x.partial_cmp(&x).is_none()
+}
+
+impl PartialEq for Rational {
+    fn eq(&self, other: &Rational) -> bool {
+        self.0.is_some() && self.0 == other.0
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => None,
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+    fn add(self, other: Rational) -> Rational { self.checked(other, |a, b| a.checked_add(b)) }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+    fn sub(self, other: Rational) -> Rational { self.checked(other, |a, b| a.checked_sub(b)) }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+    fn mul(self, other: Rational) -> Rational { self.checked(other, |a, b| a.checked_mul(b)) }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+    fn div(self, other: Rational) -> Rational { self.checked(other, |a, b| a.checked_div(b)) }
+}
+
+impl Rem for Rational {
+    type Output = Rational;
+    fn rem(self, other: Rational) -> Rational {
+        self.checked(other, |a, b| {
+            let q = a.checked_div(b)?.trunc();
+            a.checked_sub(&q.checked_mul(b)?)
+        })
+    }
+}
+
+impl Zero for Rational {
+    fn zero() -> Rational { Rational::from_integer(0) }
+    fn is_zero(&self) -> bool { self.0.is_some_and(|r| r.is_zero()) }
+}
+
+impl One for Rational {
+    fn one() -> Rational { Rational::from_integer(1) }
+}
+
+impl Num for Rational {
+    type FromStrRadixErr = ParseRatioError;
+    fn from_str_radix(s: &str, radix: u32) -> Result<Rational, ParseRatioError> {
+        Ratio::from_str_radix(s, radix).map(|r| Rational(Some(r)))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.0 {
+            Some(r) => write!(f, "{}", r),
+            None => write!(f, "overflow"),
+        }
+    }
+}
+
+impl Tolerance for Rational {
+    fn tolerance() -> Rational { Rational::zero() }
+}
+
+impl Floor for Rational {
+    fn floor(self) -> Rational {
+        Rational(self.0.map(|r| Ratio::from_integer(r.numer().div_floor(r.denom()))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_operations() {
+        let (a, b) = (Rational::new(1, 2), Rational::new(-2, 3));
+        assert_eq!(a + b, Rational::new(-1, 6));
+        assert_eq!(a / b, Rational::new(-3, 4));
+        assert_eq!(b.floor(), Rational::from_integer(-1));
+        assert!(b < a);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = Rational::new(i64::MAX, 3);
+        let x = big * Rational::from_integer(6) + Rational::one();
+        assert!(x.is_overflow() && is_unordered(x));
+        assert!(x != x && x.partial_cmp(&Rational::zero()).is_none());
+        assert!((x - x).is_overflow());
+        assert!(!is_unordered(big) && is_unordered(f64::NAN));
+    }
+}
@@ -0,0 +1,126 @@
+//! Feasibility relaxation: instead of failing on an infeasible program, find
+//! the cheapest way to violate its constraints so that it has a solution.
+//!
+//! Each row `a x <= b` becomes `a x - e <= b` and each row `a x >= b` becomes
+//! `a x + e >= b`, with a new elastic variable `e >= 0`, and the weighted sum
+//! of the elastic variables is minimized. Bounds are relaxed the same way, as
+//! rows, and equalities get one elastic variable in each direction. As in
+//! `iis`, the variables with a lower bound are free apart from it.
+
+use linear_system::*;
+use parser::LinearProgram;
+use iis::{self, Constraint};
+use error::Error;
+
+/// Values under this are not considered as violations.
+const VIOLATION_TOL: f64 = 1e-9;
+
+#[derive(Clone, Debug)]
+pub struct Relaxation {
+    pub total: f64, // weighted sum of the violations
+    pub violations: Vec<(Constraint, f64)>,
+    pub values: Vec<(usize, f64)>, // values of the variables, labelled as in `lp.to_dict()`
+}
+
+/// Minimizes the sum of the violations of the constraints and bounds of `lp`,
+/// weighted by `weight`.
+pub fn relax<W: Fn(Constraint) -> f64>(lp: &LinearProgram, weight: W,
+                                        heur: Heuristic) -> Result<Relaxation, Error> {
+    let n = lp.vars.len();
+    let mut ineqs = Vec::new();
+    let mut owner = Vec::new();
+    for c in iis::constraints(lp) {
+        for row in iis::rows_of(lp, c) {
+            ineqs.push(row);
+            owner.push(c);
+        }
+    }
+    let (mut vars, free) = iis::split_free(lp, &mut ineqs);
+    let split = vars.len();
+    let mut obj = Vec::new();
+    for (k, row) in ineqs.iter_mut().enumerate() {
+        let e = format!("e${}", k);
+        let sign = if row.kind == OrderRel::LT { -1.0 } else { 1.0 };
+        row.prods.push((sign, e.clone()));
+        obj.push((weight(owner[k]), e.clone()));
+        vars.push(e);
+    }
+
+    let elastic = LinearProgram::new(Minimize, obj, ineqs, Vec::new(), Vec::new(), vars);
+    let mut d = elastic.to_dict()?;
+    // Always feasible, and bounded since the weights are non-negative
+    let total = match d.run_simplex(heur, false) {
+        SolveResult::Optimal(z) => -z,
+        r => return Err(Error::Numerical(format!("the feasibility relaxation is {:?}", r))),
+    };
+
+    let mut values = d.values();
+    for (k, &j) in free.iter().enumerate() {
+        values[j + 1] -= values[n + k + 1];
+    }
+    let mut violations: Vec<(Constraint, f64)> = Vec::new();
+    for (k, &c) in owner.iter().enumerate() {
+        let v = values[split + k + 1];
+        if v <= VIOLATION_TOL { continue; }
+        match violations.iter_mut().find(|&&mut (o, _)| o == c) {
+            Some(&mut (_, ref mut amount)) => *amount += v,
+            None => violations.push((c, v)),
+        }
+    }
+    Ok(Relaxation {
+        total,
+        violations,
+        values: (1..n + 1).map(|l| (l, values[l])).collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    #[test]
+    fn test_relax() {
+        let r = relax(&make_infeasible(), |_| 1.0, Heuristic::Bland).unwrap();
+        assert!((r.total - 1.0).abs() < 1e-9);
+        let sum: f64 = r.violations.iter().map(|&(_, v)| v).sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+        assert!(r.violations.iter().all(|&(c, _)| c == Constraint::Row(1) || c == Constraint::Row(3)));
+    }
+
+    #[test]
+    fn test_weights() {
+        let r = relax(&make_infeasible(), |c| if c == Constraint::Row(1) { 10.0 } else { 1.0 },
+                      Heuristic::Bland).unwrap();
+        assert_eq!(r.violations.len(), 1);
+        assert_eq!(r.violations[0].0, Constraint::Row(3));
+        assert!((r.violations[0].1 - 1.0).abs() < 1e-9);
+        // x - y = 2 and x + y <= 2
+        let (x, y) = (r.values[0].1, r.values[1].1);
+        assert!((x - y - 2.0).abs() < 1e-9 && x + y <= 2.0 + 1e-9);
+    }
+
+    #[test]
+    fn test_feasible() {
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx + y = 2\nBOUNDS\n0 <= x <= 1\n\
+                                   VARIABLES\nx\ny\n").unwrap();
+        let r = relax(&lp, |_| 1.0, Heuristic::Dumb).unwrap();
+        assert_eq!(r.total, 0.0);
+        assert!(r.violations.is_empty());
+    }
+
+    #[test]
+    fn test_negative_bounds() {
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx <= -1\nBOUNDS\nx >= -5\nVARIABLES\nx\n")
+            .unwrap();
+        let r = relax(&lp, |_| 1.0, Heuristic::Bland).unwrap();
+        assert_eq!(r.total, 0.0);
+        assert!(r.violations.is_empty());
+        assert!(r.values[0].1 >= -5.0 && r.values[0].1 <= -1.0);
+
+        let lp = Parser::parse_lp("MAXIMIZE x\nSUBJECT TO\nx <= -7\nBOUNDS\nx >= -5\nVARIABLES\nx\n")
+            .unwrap();
+        let r = relax(&lp, |_| 1.0, Heuristic::Bland).unwrap();
+        assert!((r.total - 2.0).abs() < 1e-9);
+    }
+}
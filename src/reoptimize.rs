@@ -0,0 +1,193 @@
+//! Modifying a solved dictionary and re-optimizing from its basis.
+//!
+//! Constraints are given on the labels of the variables, as in the initial
+//! dictionary: they are rewritten in terms of the current non-basic variables.
+//! Adding a constraint or changing a constant keeps the basis dual feasible,
+//! so the dual simplex restores the primal feasibility; changing the objective
+//! keeps it primal feasible, so the primal simplex finishes the job.
+
+use linear_system::*;
+use error::Error;
+
+fn unknown(label: usize) -> Error {
+    Error::Model(format!("unknown variable x_{}", label))
+}
+
+impl<F: OrdField> Dictionary<F> {
+    /// Next unused label.
+    fn fresh_label(&self) -> usize {
+        self.ll.iter().chain(self.lc.iter()).cloned().max().unwrap_or(0) + 1
+    }
+
+    /// Expresses `cst + sum coeff x_label` with the current non-basic variables.
+    fn substitute(&self, cst: F, coeffs: &[(usize, F)]) -> Result<Vec<F>, Error> {
+        let mut row = init_zero_vec(self.w(), F::zero());
+        row[0] = cst;
+        for &(label, a) in coeffs.iter() {
+            if let Some(i) = self.ll.iter().position(|&x| x == label) {
+                for (j, r) in row.iter_mut().enumerate() {
+                    *r = *r + a * self.m.at(i, j);
+                }
+            } else if let Some(j) = self.lc.iter().position(|&x| x == label) {
+                row[j] = row[j] + a;
+            } else {
+                return Err(unknown(label));
+            }
+        }
+        Ok(row)
+    }
+
+    /// Adds the line `x_new = cst + sum coeff x_label` (so that `x_new >= 0` is
+    /// the new constraint) and returns the label of `x_new`.
+    pub fn add_row(&mut self, cst: F, coeffs: &[(usize, F)]) -> Result<usize, Error> {
+        let row = self.substitute(cst, coeffs)?;
+        let label = self.fresh_label();
+        self.m.m.truncate(self.m.h * self.m.w);
+        self.m.m.extend(row);
+        self.m.h += 1;
+        self.ll.push(label);
+        Ok(label)
+    }
+
+    /// Adds the constraint `sum coeff x_label (rel) rhs` and returns the labels
+    /// of the new slack variables (two of them for an equality).
+    pub fn add_constraint(&mut self, coeffs: &[(usize, F)], rel: OrderRel,
+                          rhs: F) -> Result<Vec<usize>, Error> {
+        let neg: Vec<(usize, F)> = coeffs.iter().map(|&(l, a)| (l, F::zero() - a)).collect();
+        Ok(match rel {
+            OrderRel::LT => vec![self.add_row(rhs, &neg)?],
+            OrderRel::GT => vec![self.add_row(F::zero() - rhs, coeffs)?],
+            OrderRel::EQ => vec![self.add_row(rhs, &neg)?, self.add_row(F::zero() - rhs, coeffs)?],
+        })
+    }
+
+    /// Forces `x_label = val`.
+    pub fn fix_var(&mut self, label: usize, val: F) -> Result<Vec<usize>, Error> {
+        self.add_constraint(&[(label, F::one())], OrderRel::EQ, val)
+    }
+
+    /// Adds `delta` to the constant of the line of the (slack) variable `x_label`
+    /// in the initial dictionary. For a `<=` constraint, this is adding `delta`
+    /// to its right hand side, for a `>=` one, it is subtracting it.
+    pub fn shift_rhs(&mut self, label: usize, delta: F) -> Result<(), Error> {
+        if let Some(i) = self.ll.iter().position(|&x| x == label) {
+            let old = self.m.at(i, 0);
+            self.m.set_at(i, 0, old + delta);
+        } else if let Some(j) = self.lc.iter().position(|&x| x == label) {
+            // x_label = x'_label - delta where x'_label is the new slack
+            for i in 0..self.h() {
+                let old = self.m.at(i, 0);
+                let a = self.m.at(i, j);
+                self.m.set_at(i, 0, old - a * delta);
+            }
+            self.obj[0] = self.obj[0] - self.obj[j] * delta;
+        } else {
+            return Err(unknown(label));
+        }
+        Ok(())
+    }
+
+    /// Adds `delta` to the coefficient of `x_label` in the (maximized) objective.
+    pub fn shift_obj(&mut self, label: usize, delta: F) -> Result<(), Error> {
+        let row = self.substitute(F::zero(), &[(label, delta)])?;
+        for (o, r) in self.obj.iter_mut().zip(row) {
+            *o = *o + r;
+        }
+        Ok(())
+    }
+
+    pub fn is_primal_feasible(&self) -> bool {
+        (0..self.h()).all(|i| self.m.at(i, 0) >= F::zero() - F::tolerance())
+    }
+
+    pub fn is_dual_feasible(&self) -> bool {
+        self.obj[1..].iter().all(|&c| c <= F::tolerance())
+    }
+
+    /// The dual simplex, starting from a dual feasible dictionary.
+    pub fn run_dual_simplex(&mut self) -> SolveResult<F> {
+        loop {
+            let mut leaving = None;
+            for i in 0..self.h() {
+                let b = self.m.at(i, 0);
+                if b < F::zero() - F::tolerance() && leaving.is_none_or(|(_, min)| b < min) {
+                    leaving = Some((i, b));
+                }
+            }
+            let il = match leaving {
+                Some((il, _)) => il,
+                None => return SolveResult::Optimal(self.obj[0]),
+            };
+
+            let mut entering = None;
+            for j in 1..self.w() {
+                let a = self.m.at(il, j);
+                if a > F::tolerance() {
+                    let ratio = (F::zero() - self.obj[j]) / a;
+                    if entering.is_none_or(|(_, min)| ratio < min) {
+                        entering = Some((j, ratio));
+                    }
+                }
+            }
+            match entering {
+                Some((je, _)) => self.perform_pivot(je, il),
+                None => return SolveResult::Infeasible, // x_ll[il] < 0 whatever we do
+            }
+        }
+    }
+
+    /// Re-optimizes after some modifications, starting from the current basis.
+    pub fn reoptimize(&mut self, heur: Heuristic) -> SolveResult<F> {
+        if !self.is_primal_feasible() && self.is_dual_feasible() {
+            if let SolveResult::Infeasible = self.run_dual_simplex() {
+                return SolveResult::Infeasible;
+            }
+        }
+        self.run_simplex(heur, false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use linear_system::*;
+
+    fn solved_dict() -> Dictionary<f64> {
+        let mut d = make_dict();
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(24.0));
+        d
+    }
+
+    #[test]
+    fn test_add_constraint() {
+        let mut d = solved_dict();
+        d.add_constraint(&[(2, 1.0)], OrderRel::LT, 2.0).unwrap();
+        d.check_integrity().unwrap();
+        assert!(!d.is_primal_feasible());
+        assert_eq!(d.run_dual_simplex(), SolveResult::Optimal(20.0));
+    }
+
+    #[test]
+    fn test_shift_rhs() {
+        let mut d = solved_dict();
+        d.shift_rhs(4, 4.0).unwrap();
+        assert_eq!(d.reoptimize(Heuristic::Bland), SolveResult::Optimal(32.0));
+        d.shift_rhs(4, -20.0).unwrap();
+        assert_eq!(d.reoptimize(Heuristic::Bland), SolveResult::Infeasible);
+    }
+
+    #[test]
+    fn test_shift_obj() {
+        let mut d = solved_dict();
+        d.shift_obj(1, 10.0).unwrap();
+        assert_eq!(d.reoptimize(Heuristic::Bland), SolveResult::Optimal(52.0));
+    }
+
+    #[test]
+    fn test_fix_var() {
+        let mut d = solved_dict();
+        d.fix_var(2, 1.0).unwrap();
+        let opt = d.reoptimize(Heuristic::Bland).optimum().unwrap();
+        assert!((opt - 16.0).abs() < 1e-12);
+        assert!(d.fix_var(42, 1.0).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Scaling of the rows and columns of a dictionary, so that its coefficients
+//! have magnitudes close to one before the simplex starts.
+//!
+//! A scaling gives a factor `f` to each label, and the scaled variables are
+//! `x' = x / f`. The line of the basic variable `x_p` is then divided by `f_p`
+//! and the column of the non-basic `x_q` multiplied by `f_q`, so a factor on a
+//! slack scales a row of the constraint matrix and a factor on a variable
+//! scales a column. The objective value is not changed; the values of the
+//! variables are multiplied back by `f` and the dual values divided by `f`.
+//!
+//! The factors are powers of two, so that scaling does not add rounding
+//! errors.
+
+use linear_system::*;
+use interior_point::IpmSolution;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScalingMethod {
+    GeometricMean, // repeatedly divides by the geometric mean of the extreme coefficients
+    Equilibration, // divides the rows, then the columns, by their largest coefficient
+}
+
+/// Passes of the geometric mean scaling.
+const GEOMETRIC_PASSES: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scaling {
+    factors: Vec<f64>, // indexed by label
+}
+
+fn pow2(x: f64) -> f64 {
+    2f64.powi(x.log2().round() as i32)
+}
+
+/// Smallest and largest non-zero magnitudes of `coeffs`.
+fn extremes<I: Iterator<Item=f64>>(coeffs: I) -> Option<(f64, f64)> {
+    coeffs.map(|a| a.abs()).filter(|&a| a != 0.0).fold(None, |acc, a| match acc {
+        None => Some((a, a)),
+        Some((min, max)) => Some((min.min(a), max.max(a))),
+    })
+}
+
+impl Scaling {
+    /// Computes the scaling of the constraint matrix of `d`, whose rows are
+    /// the lines and whose columns are the non-basic variables.
+    pub fn new(d: &Dictionary<f64>, method: ScalingMethod) -> Scaling {
+        let mut row: Vec<f64> = init_zero_vec(d.h(), 1.0);
+        let mut col: Vec<f64> = init_zero_vec(d.w(), 1.0);
+        let norm = |ext: Option<(f64, f64)>| match (method, ext) {
+            (_, None) => 1.0,
+            (ScalingMethod::GeometricMean, Some((min, max))) => pow2(1.0 / (min * max).sqrt()),
+            (ScalingMethod::Equilibration, Some((_, max))) => pow2(1.0 / max),
+        };
+        let passes = match method {
+            ScalingMethod::GeometricMean => GEOMETRIC_PASSES,
+            ScalingMethod::Equilibration => 1,
+        };
+        for _ in 0..passes {
+            for (i, r) in row.iter_mut().enumerate() {
+                *r = norm(extremes((1..d.w()).map(|j| d.m.at(i, j) * col[j])));
+            }
+            for (j, c) in col.iter_mut().enumerate().skip(1) {
+                *c = norm(extremes((0..d.h()).map(|i| row[i] * d.m.at(i, j))));
+            }
+        }
+
+        let n = d.ll.iter().chain(d.lc.iter()).cloned().max().unwrap_or(0) + 1;
+        let mut factors = init_zero_vec(n, 1.0);
+        for (i, &r) in row.iter().enumerate() {
+            factors[d.ll[i]] = 1.0 / r;
+        }
+        for (j, &c) in col.iter().enumerate().skip(1) {
+            factors[d.lc[j]] = c;
+        }
+        Scaling { factors }
+    }
+
+    fn factor(&self, label: usize) -> f64 {
+        self.factors.get(label).cloned().unwrap_or(1.0)
+    }
+
+    /// Replaces each `x_l` by `f(l) x_l` in `d`.
+    fn substitute<G: Fn(usize) -> f64>(d: &mut Dictionary<f64>, f: G) {
+        for i in 0..d.h() {
+            let fp = f(d.ll[i]);
+            for j in 0..d.w() {
+                let a = d.m.at(i, j) * f(d.lc[j]) / fp;
+                d.m.set_at(i, j, a);
+            }
+        }
+        for j in 0..d.w() {
+            d.obj[j] *= f(d.lc[j]);
+        }
+    }
+
+    pub fn scale(&self, d: &mut Dictionary<f64>) {
+        Scaling::substitute(d, |l| if l == 0 { 1.0 } else { self.factor(l) });
+    }
+
+    /// Gives back the dictionary of the original program, with the same basis.
+    pub fn unscale(&self, d: &mut Dictionary<f64>) {
+        Scaling::substitute(d, |l| if l == 0 { 1.0 } else { 1.0 / self.factor(l) });
+    }
+
+    /// Unscales values indexed by label.
+    pub fn unscale_primal(&self, x: &mut [f64]) {
+        for (l, v) in x.iter_mut().enumerate().skip(1) {
+            *v *= self.factor(l);
+        }
+    }
+
+    /// Unscales dual values or reduced costs indexed by label.
+    pub fn unscale_dual(&self, z: &mut [f64]) {
+        for (l, v) in z.iter_mut().enumerate().skip(1) {
+            *v /= self.factor(l);
+        }
+    }
+
+    /// Unscales the solution of the interior-point method on the scaled `d`.
+    pub fn unscale_ipm(&self, d: &Dictionary<f64>, sol: &mut IpmSolution) {
+        self.unscale_primal(&mut sol.x);
+        self.unscale_dual(&mut sol.z);
+        for (i, y) in sol.y.iter_mut().enumerate() {
+            *y /= self.factor(d.ll[i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn badly_scaled() -> Dictionary<f64> {
+        // max 3 x1 + 8000 x2 st 0.001 x1 + 2 x2 <= 8, 12000 x1 + 4000 x2 <= 12000
+        Dictionary {
+            m: Matrix { h: 2, w: 3, m: vec![8., -0.001, -2., 12000., -12000., -4000.] },
+            ll: vec![3, 4],
+            lc: vec![0, 1, 2],
+            obj: vec![0., 3., 8000.],
+            weq: vec![0., 0., 0.],
+            var_name: "x",
+        }
+    }
+
+    fn spread(d: &Dictionary<f64>) -> f64 {
+        let (min, max) = extremes((0..d.h()).flat_map(|i| (1..d.w()).map(move |j| (i, j)))
+                                  .map(|(i, j)| d.m.at(i, j))).unwrap();
+        max / min
+    }
+
+    #[test]
+    fn test_geometric_mean() {
+        let orig = badly_scaled();
+        let mut d = orig.clone();
+        let s = Scaling::new(&d, ScalingMethod::GeometricMean);
+        s.scale(&mut d);
+        assert!(spread(&d) < spread(&orig) / 100.0);
+
+        let opt = d.run_simplex(Heuristic::Bland, false).optimum().unwrap();
+        s.unscale(&mut d);
+        let mut expected = orig.clone();
+        let opt2 = expected.run_simplex(Heuristic::Bland, false).optimum().unwrap();
+        assert!((opt - opt2).abs() < 1e-9 * opt2);
+        let (values, exp_values) = (d.values(), expected.values());
+        for (v, e) in values.iter().zip(exp_values.iter()) {
+            assert!((v - e).abs() < 1e-9 * e.abs().max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_equilibration() {
+        let mut d = badly_scaled();
+        let s = Scaling::new(&d, ScalingMethod::Equilibration);
+        s.scale(&mut d);
+        for j in 1..d.w() {
+            let max = (0..d.h()).map(|i| d.m.at(i, j).abs()).fold(0.0, f64::max);
+            assert!(max > 0.5 && max < 1.5);
+        }
+        let mut back = d.clone();
+        s.unscale(&mut back);
+        assert_eq!(back, badly_scaled()); // powers of two are exact
+    }
+
+    #[test]
+    fn test_unscale_ipm() {
+        use interior_point::InteriorPoint;
+        let orig = badly_scaled();
+        let mut d = orig.clone();
+        let s = Scaling::new(&d, ScalingMethod::GeometricMean);
+        s.scale(&mut d);
+        let mut sol = InteriorPoint::new().solve(&d);
+        s.unscale_ipm(&d, &mut sol);
+        let reference = InteriorPoint::new().solve(&orig);
+        for (x, e) in sol.x.iter().zip(reference.x.iter()) {
+            assert!((x - e).abs() < 1e-5 * e.abs().max(1.0));
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! The canonical form `max c x` subject to `A x <= b` and `x >= 0` of a
+//! linear program, with the mapping from its variables and rows back to the
+//! original ones.
+//!
+//! Each variable is shifted by its lower bound, `x = x' + l`, so that it is
+//! non-negative, and its upper bound becomes the row `x' <= u - l`. The `>=`
+//! rows are negated, equalities are split into two rows and a minimization is
+//! written as the maximization of the opposite objective. The shifts leave a
+//! constant in the objective, which the canonical form cannot hold.
+//!
+//! The bounds of integer variables are rounded to integers first, so that the
+//! shifted variables are integral exactly when the original ones are.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use linear_system::*;
+use parser::LinearProgram;
+use branch_bound::INT_TOL;
+use error::Error;
+
+/// Where a row of the standard form comes from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RowOrigin {
+    Row(usize), // a `<=` row of `ineqs`, as it is
+    Negated(usize), // a `>=` row, negated
+    EqualityLe(usize), // the `<=` half of an equality
+    EqualityGe(usize), // the `>=` half of an equality, negated
+    Upper(usize), // upper bound of the variable `vars[j]`
+}
+
+impl RowOrigin {
+    /// The relation of the original row.
+    pub fn kind(&self) -> OrderRel {
+        match *self {
+            RowOrigin::Negated(_) | RowOrigin::EqualityGe(_) => OrderRel::GT,
+            _ => OrderRel::LT,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StandardForm {
+    pub lp: NormalLinearProgram<f64>, // always a maximization
+    pub negated: bool, // the original program is a minimization
+    pub shifts: Vec<f64>, // `vars[j] = lp.names[j] + shifts[j]`
+    pub rows: Vec<RowOrigin>,
+}
+
+pub fn standard_form(lp: &LinearProgram) -> Result<StandardForm, Error> {
+    let n = lp.vars.len();
+    let idx: HashMap<&str, usize> = lp.vars.iter().enumerate().map(|(j, x)| (&x[..], j)).collect();
+    let find = |x: &str| idx.get(x).cloned().ok_or_else(|| Error::Model(format!("unknown variable {}", x)));
+    if let Some((_, x)) = lp.vars.iter().enumerate().find(|&(j, x)| idx[&x[..]] != j) {
+        return Err(Error::Model(format!("variable {} is defined twice", x)));
+    }
+
+    let mut lower: Vec<f64> = init_zero_vec(n, 0.0);
+    let mut upper: Vec<Option<f64>> = init_zero_vec(n, None);
+    for b in lp.bounds.iter() {
+        let j = find(&b.var)?;
+        if let Some(l) = b.lower {
+            lower[j] = l;
+        }
+        if let Some(u) = b.upper {
+            upper[j] = Some(upper[j].map_or(u, |v: f64| v.min(u)));
+        }
+    }
+    for x in lp.integers.iter() {
+        let j = find(x)?;
+        lower[j] = (lower[j] - INT_TOL).ceil();
+        upper[j] = upper[j].map(|u| (u + INT_TOL).floor());
+    }
+
+    let mut ineqs = Vec::new();
+    let mut rows = Vec::new();
+    let mut push = |coeffs: Vec<f64>, cst: f64, origin| {
+        ineqs.push(Inequation { coeffs, order: OrderRel::LT, cst });
+        rows.push(origin);
+    };
+    for (k, ineq) in lp.ineqs.iter().enumerate() {
+        let mut coeffs: Vec<f64> = init_zero_vec(n, 0.0);
+        let mut cst = ineq.cst;
+        for &(a, ref x) in ineq.prods.iter() {
+            let j = find(x)?;
+            coeffs[j] += a;
+            cst -= a * lower[j];
+        }
+        let negated = || (coeffs.iter().map(|&a| -a).collect(), -cst);
+        match ineq.kind {
+            OrderRel::LT => push(coeffs.clone(), cst, RowOrigin::Row(k)),
+            OrderRel::GT => {
+                let (a, b) = negated();
+                push(a, b, RowOrigin::Negated(k));
+            },
+            OrderRel::EQ => {
+                let (a, b) = negated();
+                push(coeffs.clone(), cst, RowOrigin::EqualityLe(k));
+                push(a, b, RowOrigin::EqualityGe(k));
+            },
+        }
+    }
+    for (j, u) in upper.iter().enumerate() {
+        if let Some(u) = *u {
+            let mut coeffs: Vec<f64> = init_zero_vec(n, 0.0);
+            coeffs[j] = 1.0;
+            push(coeffs, u - lower[j], RowOrigin::Upper(j));
+        }
+    }
+
+    let mult = match lp.goal {
+        Maximize => 1.0,
+        Minimize => -1.0,
+    };
+    let mut obj: Vec<f64> = init_zero_vec(n, 0.0);
+    let mut obj_cst = lp.obj_cst;
+    for &(c, ref x) in lp.obj.iter() {
+        let j = find(x)?;
+        obj[j] += mult * c;
+        obj_cst += mult * c * lower[j];
+    }
+
+    let names = lp.vars.iter().zip(lower.iter())
+        .map(|(x, &l)| if l == 0.0 { x.clone() } else { format!("{}'", x) })
+        .collect();
+    let nlp = NormalLinearProgram { ineqs, obj, obj_cst, obj_kind: Maximize, names };
+    nlp.check_integrity()?;
+    Ok(StandardForm {
+        lp: nlp,
+        negated: lp.goal == Minimize,
+        shifts: lower,
+        rows,
+    })
+}
+
+impl StandardForm {
+    /// Gives back the values of the original variables from the ones of the
+    /// dictionary of `self.lp`, indexed by label.
+    pub fn unshift(&self, values: &[(usize, f64)]) -> Vec<(usize, f64)> {
+        values.iter().map(|&(l, v)| (l, v + self.shift(l))).collect()
+    }
+
+    /// Shift of the variable labelled `l`, 0 for the slacks.
+    pub fn shift(&self, l: usize) -> f64 {
+        if (1..=self.shifts.len()).contains(&l) { self.shifts[l - 1] } else { 0.0 }
+    }
+
+    /// The dual value of each of the `m` rows of the original program and the
+    /// reduced cost of each variable, read from an optimal dictionary of
+    /// `self.lp`. They are the rates of change of the original objective when
+    /// the constant of the row, or the variable, increases.
+    pub fn marginals(&self, m: usize, d: &Dictionary<f64>) -> (Vec<f64>, Vec<f64>) {
+        let n = self.shifts.len();
+        let costs = d.reduced_costs();
+        let cost = |l: usize| {
+            let c = costs.get(l).cloned().unwrap_or(0.0);
+            if self.negated { 0.0 - c } else { c }
+        };
+        let mut duals = init_zero_vec(m, 0.0);
+        let mut reduced: Vec<f64> = (1..=n).map(cost).collect();
+        // The slack of the row `r` is labelled `n + 1 + r`, and increasing
+        // it has the same effect as increasing the constant of the row
+        for (r, &origin) in self.rows.iter().enumerate() {
+            let c = cost(n + 1 + r);
+            match origin {
+                RowOrigin::Row(k) | RowOrigin::EqualityLe(k) => duals[k] -= c,
+                RowOrigin::Negated(k) | RowOrigin::EqualityGe(k) => duals[k] += c,
+                RowOrigin::Upper(j) => reduced[j] -= c,
+            }
+        }
+        (duals, reduced)
+    }
+
+    /// The standard form followed by the mapping tables, `lp` being the
+    /// original program.
+    pub fn report(&self, lp: &LinearProgram) -> String {
+        let mut s = format!("{}\nVariable mapping\n\n", self.lp);
+        for (j, x) in lp.vars.iter().enumerate() {
+            let (t, x2) = (self.shifts[j], &self.lp.names[j]);
+            let _ = if t == 0.0 {
+                writeln!(s, "{} = {}", x, x2)
+            } else if t < 0.0 {
+                writeln!(s, "{} = {} - {}", x, x2, -t)
+            } else {
+                writeln!(s, "{} = {} + {}", x, x2, t)
+            };
+        }
+
+        s.push_str("\nRow mapping\n\n");
+        for (i, &origin) in self.rows.iter().enumerate() {
+            let _ = match origin {
+                RowOrigin::Row(k) => writeln!(s, "{}: constraint {}", i + 1, k + 1),
+                RowOrigin::Negated(k) => writeln!(s, "{}: constraint {}, negated", i + 1, k + 1),
+                RowOrigin::EqualityLe(k) =>
+                    writeln!(s, "{}: constraint {}, <= half of the equality", i + 1, k + 1),
+                RowOrigin::EqualityGe(k) =>
+                    writeln!(s, "{}: constraint {}, >= half of the equality, negated", i + 1, k + 1),
+                RowOrigin::Upper(j) => writeln!(s, "{}: upper bound of {}", i + 1, lp.vars[j]),
+            };
+        }
+
+        let _ = if self.negated {
+            writeln!(s, "\nObjective: opposite of the original one, plus the constant {}", self.lp.obj_cst)
+        } else {
+            writeln!(s, "\nObjective: the original one, plus the constant {}", self.lp.obj_cst)
+        };
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    fn example() -> LinearProgram {
+        Parser::parse_lp("MINIMIZE x + y\nSUBJECT TO\nx + y >= 2\nx - 2 y = 0\n\
+                          BOUNDS\nx >= 1\n0 <= y <= 5\nVARIABLES\nx\ny\n").unwrap()
+    }
+
+    #[test]
+    fn test_standard_form() {
+        let sf = standard_form(&example()).unwrap();
+        assert_eq!(sf.lp.names, vec!["x'", "y"]);
+        assert_eq!(sf.shifts, vec![1.0, 0.0]);
+        assert_eq!(sf.rows, vec![RowOrigin::Negated(0), RowOrigin::EqualityLe(1),
+                                 RowOrigin::EqualityGe(1), RowOrigin::Upper(1)]);
+        // x' + 1 + y >= 2 becomes -x' - y <= -1
+        assert_eq!(sf.lp.ineqs[0], Inequation { coeffs: vec![-1.0, -1.0], order: OrderRel::LT, cst: -1.0 });
+        assert_eq!(sf.lp.ineqs[1].cst, -1.0);
+        assert_eq!(sf.lp.obj, vec![-1.0, -1.0]);
+        assert_eq!(sf.lp.obj_cst, -1.0);
+        assert!(sf.negated);
+    }
+
+    #[test]
+    fn test_same_optimum() {
+        // The optimum of the original program is 2, at x = 4/3, y = 2/3
+        let sf = standard_form(&example()).unwrap();
+        let z = Parser::parse_lp(&sf.lp.to_string()).unwrap().to_dict().unwrap()
+            .run_simplex(Heuristic::Bland, false).optimum().unwrap();
+        assert!((-(z + sf.lp.obj_cst) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_report() {
+        let lp = example();
+        let report = standard_form(&lp).unwrap().report(&lp);
+        assert!(report.contains("x = x' + 1\ny = y\n"));
+        assert!(report.contains("3: constraint 2, >= half of the equality, negated\n4: upper bound of y\n"));
+        assert!(standard_form(&Parser::parse_lp("MAXIMIZE z\nSUBJECT TO\nBOUNDS\nVARIABLES\nx\n").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_marginals() {
+        // x + y <= 4 is tight at the optimum x = 3, y = 1, and so is the bound of x
+        let src = "{}\n3 x + 2 y\nSUBJECT TO\nx + y <= 4\nx + 3 y <= 9\nBOUNDS\nx <= 3\nVARIABLES\nx\ny\n";
+        let lp = Parser::parse_lp(&src.replace("{}", "MAXIMIZE")).unwrap();
+        let sf = standard_form(&lp).unwrap();
+        let mut d = sf.lp.to_dict();
+        assert_eq!(d.run_simplex(Heuristic::Bland, false), SolveResult::Optimal(11.0));
+        assert_eq!(sf.marginals(2, &d), (vec![2.0, 0.0], vec![1.0, 0.0]));
+
+        let lp = Parser::parse_lp(&src.replace("{}", "MINIMIZE").replace("3 x + 2 y", "-3 x - 2 y")).unwrap();
+        let sf = standard_form(&lp).unwrap();
+        let mut d = sf.lp.to_dict();
+        d.run_simplex(Heuristic::Bland, false);
+        assert_eq!(sf.marginals(2, &d), (vec![-2.0, 0.0], vec![-1.0, 0.0]));
+    }
+}
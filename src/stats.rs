@@ -0,0 +1,147 @@
+//! Statistics of a run of the simplex, collected by an observer.
+//!
+//! There are no bound flips to count: the upper bounds are rows of the
+//! dictionary, so they are only reached through pivots.
+
+use std::fmt::{Display, Formatter, Error};
+use std::time::{Duration, Instant};
+
+use linear_system::*;
+use observer::{Observer, Phase, Pivot};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct PhaseStats {
+    pub pivots: usize,
+    pub degenerate: usize,
+    pub time: Duration,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Stats<F> {
+    pub lines: usize,
+    pub columns: usize, // without the constant
+    pub nonzeros: usize,
+    pub first: Option<PhaseStats>,
+    pub second: Option<PhaseStats>,
+    pub min_pivot: Option<F>, // smallest magnitude of a pivot element
+    pub max_pivot: Option<F>,
+    started: Option<Instant>,
+}
+
+impl<F: OrdField> Stats<F> {
+    /// Statistics for the run of the simplex on `d`.
+    pub fn new(d: &Dictionary<F>) -> Stats<F> {
+        let nonzeros = (0..d.h())
+            .map(|i| (1..d.w()).filter(|&j| d.m.at(i, j) != F::zero()).count())
+            .sum();
+        Stats {
+            lines: d.h(),
+            columns: d.w() - 1,
+            nonzeros,
+            first: None,
+            second: None,
+            min_pivot: None,
+            max_pivot: None,
+            started: None,
+        }
+    }
+
+    pub fn density(&self) -> f64 {
+        if self.lines * self.columns == 0 {
+            0.0
+        } else {
+            self.nonzeros as f64 / (self.lines * self.columns) as f64
+        }
+    }
+
+    fn phase_mut(&mut self, phase: Phase) -> &mut PhaseStats {
+        let p = match phase {
+            Phase::First => &mut self.first,
+            Phase::Second => &mut self.second,
+        };
+        p.get_or_insert_with(PhaseStats::default)
+    }
+}
+
+impl<F: OrdField> Observer<F> for Stats<F> {
+    fn phase_start(&mut self, phase: Phase, _d: &Dictionary<F>) {
+        self.phase_mut(phase);
+        self.started = Some(Instant::now());
+    }
+
+    fn pivot(&mut self, p: &Pivot<F>, _d: &Dictionary<F>) {
+        self.phase_mut(p.phase).pivots += 1;
+        let a = abs(p.element);
+        if self.min_pivot.is_none_or(|min| a < min) {
+            self.min_pivot = Some(a);
+        }
+        if self.max_pivot.is_none_or(|max| a > max) {
+            self.max_pivot = Some(a);
+        }
+    }
+
+    fn degenerate(&mut self, p: &Pivot<F>) {
+        self.phase_mut(p.phase).degenerate += 1;
+    }
+
+    fn phase_end(&mut self, phase: Phase, _d: &Dictionary<F>) {
+        if let Some(start) = self.started.take() {
+            self.phase_mut(phase).time = start.elapsed();
+        }
+    }
+}
+
+impl<F: OrdField> Display for Stats<F> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        writeln!(f, "Dictionary: {} lines, {} columns, {} non-zeros ({:.1}% dense)",
+                 self.lines, self.columns, self.nonzeros, 100.0 * self.density())?;
+        for (name, phase) in [("First", &self.first), ("Second", &self.second)].iter() {
+            if let Some(ref p) = **phase {
+                writeln!(f, "{} phase: {} pivots, {} degenerate, in {:.3?}", name, p.pivots,
+                         p.degenerate, p.time)?;
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_pivot, self.max_pivot) {
+            writeln!(f, "Pivot elements: between {} and {} in magnitude", min, max)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut d = make_dict();
+        let mut stats = Stats::new(&d);
+        assert_eq!((stats.lines, stats.columns, stats.nonzeros), (2, 2, 4));
+        d.run_simplex_with(Heuristic::Bland, &mut stats);
+        assert_eq!(stats.first, None);
+        let second = stats.second.unwrap();
+        assert_eq!((second.pivots, second.degenerate), (2, 0));
+        // The pivots are -3 then -4/3
+        assert_eq!(stats.max_pivot, Some(3.));
+        assert!((stats.min_pivot.unwrap() - 4. / 3.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_first_phase() {
+        // max x st x >= 1, x <= 2, with a zero coefficient
+        let mut d = Dictionary {
+            m: Matrix { h: 2, w: 3, m: vec![-1., 1., 0., 2., -1., 0.] },
+            ll: vec![3, 4],
+            lc: vec![0, 1, 2],
+            obj: vec![0., 1., 0.],
+            weq: vec![0., 0., 0.],
+            var_name: "x",
+        };
+        let mut stats = Stats::new(&d);
+        assert_eq!(stats.density(), 0.5);
+        d.run_simplex_with(Heuristic::Bland, &mut stats);
+        assert!(stats.first.unwrap().pivots >= 1);
+        assert!(stats.second.is_some());
+        assert!(stats.to_string().starts_with("Dictionary: 2 lines, 2 columns, 2 non-zeros (50.0% dense)\n"));
+    }
+}
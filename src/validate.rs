@@ -0,0 +1,223 @@
+//! Checks of a program before it is solved, which report all its issues at
+//! once with the lines where they are written.
+//!
+//! Errors prevent the program from being solved: undeclared variables,
+//! coefficients which are not finite and contradictory bounds. Warnings are
+//! likely mistakes which do not: variables appearing nowhere and bounds
+//! declared several times, of which the last lower bound and the smallest
+//! upper bound are kept (see `standard_form`).
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use parser::{LinearProgram, Locations};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub line: Option<usize>, // None for a program which was not parsed
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+struct Checker<'a> {
+    loc: Option<&'a Locations>,
+    declared: HashMap<&'a str, usize>,
+    used: Vec<bool>,
+    issues: Vec<Issue>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, severity: Severity, line: Option<usize>, message: String) {
+        self.issues.push(Issue { severity, line, message });
+    }
+
+    /// Checks the terms `prods` of the objective or of a constraint.
+    fn terms(&mut self, what: &str, line: Option<usize>, prods: &[(f64, String)]) {
+        for &(a, ref x) in prods.iter() {
+            match self.declared.get(&x[..]) {
+                Some(&j) => self.used[j] = true,
+                None => {
+                    self.report(Severity::Error, line,
+                                format!("undeclared variable {} in {}", x, what));
+                },
+            }
+            if !a.is_finite() {
+                self.report(Severity::Error, line,
+                            format!("the coefficient of {} in {} is {}", x, what, a));
+            }
+        }
+    }
+}
+
+/// All the issues of `lp`, sorted by line. `loc` gives the lines of a parsed
+/// program, see `Parser::parse_lp_with_locations`.
+pub fn validate(lp: &LinearProgram, loc: Option<&Locations>) -> Vec<Issue> {
+    let mut c = Checker {
+        loc,
+        declared: HashMap::new(),
+        used: vec![false; lp.vars.len()],
+        issues: Vec::new(),
+    };
+    let var_line = |j: usize| loc.map(|l| l.vars[j]);
+
+    for (j, x) in lp.vars.iter().enumerate() {
+        if let Some(&i) = c.declared.get(&x[..]) {
+            let msg = match var_line(i) {
+                Some(first) => format!("variable {} is declared twice, first on line {}", x, first),
+                None => format!("variable {} is declared twice", x),
+            };
+            c.report(Severity::Error, var_line(j), msg);
+        } else {
+            c.declared.insert(x, j);
+        }
+    }
+
+    c.terms("the objective", loc.map(|l| l.obj), &lp.obj);
+    for (k, ineq) in lp.ineqs.iter().enumerate() {
+        let what = format!("constraint {}", k + 1);
+        let line = c.loc.map(|l| l.ineqs[k]);
+        c.terms(&what, line, &ineq.prods);
+        if !ineq.cst.is_finite() {
+            c.report(Severity::Error, line, format!("the right-hand side of {} is {}", what, ineq.cst));
+        }
+    }
+    for (k, x) in lp.integers.iter().enumerate() {
+        if !c.declared.contains_key(&x[..]) {
+            let line = c.loc.map(|l| l.integers[k]);
+            c.report(Severity::Error, line, format!("undeclared integer variable {}", x));
+        }
+    }
+
+    // The bounds of each variable as `standard_form` combines them, and the
+    // line of their first declaration
+    let mut lower = vec![0.0; lp.vars.len()];
+    let mut upper: Vec<Option<f64>> = vec![None; lp.vars.len()];
+    let mut bounded = vec![false; lp.vars.len()];
+    let mut first_line: Vec<Option<usize>> = vec![None; lp.vars.len()];
+    for (k, b) in lp.bounds.iter().enumerate() {
+        let line = c.loc.map(|l| l.bounds[k]);
+        for &v in b.lower.iter().chain(b.upper.iter()) {
+            if !v.is_finite() {
+                c.report(Severity::Error, line, format!("the bound {} of {} is not finite", v, b.var));
+            }
+        }
+        let j = match c.declared.get(&b.var[..]) {
+            Some(&j) => j,
+            None => {
+                c.report(Severity::Error, line, format!("bound on undeclared variable {}", b.var));
+                continue;
+            },
+        };
+        if bounded[j] {
+            let msg = match first_line[j] {
+                Some(first) => format!("the bounds of {} are declared again, first on line {}",
+                                       b.var, first),
+                None => format!("the bounds of {} are declared twice", b.var),
+            };
+            c.report(Severity::Warning, line, msg);
+        } else {
+            bounded[j] = true;
+            first_line[j] = line;
+        }
+        if let Some(l) = b.lower {
+            lower[j] = l;
+        }
+        if let Some(u) = b.upper {
+            upper[j] = Some(upper[j].map_or(u, |v: f64| v.min(u)));
+        }
+        if let Some(u) = upper[j] {
+            if lower[j] > u {
+                c.report(Severity::Error, line,
+                         format!("the bounds of {} contradict each other: {} <= {} <= {}",
+                                 b.var, lower[j], b.var, u));
+            }
+        }
+    }
+
+    for (j, x) in lp.vars.iter().enumerate() {
+        if !c.used[j] && c.declared.get(&x[..]) == Some(&j) {
+            c.report(Severity::Warning, var_line(j),
+                     format!("variable {} appears in no constraint nor in the objective", x));
+        }
+    }
+
+    let mut issues = c.issues;
+    issues.sort_by_key(|i| i.line);
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+
+    fn issues(src: &str) -> Vec<(Severity, Option<usize>)> {
+        let (lp, loc) = Parser::parse_lp_with_locations(src).unwrap();
+        validate(&lp, Some(&loc)).iter().map(|i| (i.severity, i.line)).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let src = "MAXIMIZE\nx + y\nSUBJECT TO\nx + y <= 3\nBOUNDS\nx <= 2\nVARIABLES\nx\ny\n";
+        assert_eq!(issues(src), vec![]);
+    }
+
+    #[test]
+    fn test_variables() {
+        // z is undeclared, w and t are unused and u only appears in a bound
+        let src = "MAXIMIZE\nx + y\nSUBJECT TO\nx + z <= 3\nBOUNDS\nu <= 2\nGENERAL\nv\n\
+                   VARIABLES\nx\ny\nw\nt\n";
+        let (lp, loc) = Parser::parse_lp_with_locations(src).unwrap();
+        let found = validate(&lp, Some(&loc));
+        let messages: Vec<String> = found.iter().map(|i| i.to_string()).collect();
+        assert_eq!(messages, vec![
+            "line 4: undeclared variable z in constraint 1",
+            "line 6: bound on undeclared variable u",
+            "line 8: undeclared integer variable v",
+            "line 12: variable w appears in no constraint nor in the objective",
+            "line 13: variable t appears in no constraint nor in the objective",
+        ]);
+        assert_eq!(found[3].severity, Severity::Warning);
+        assert!(found[..3].iter().all(|i| i.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_bounds() {
+        // x is bounded twice, the second time below its lower bound, and the
+        // upper bound of y is below its default lower bound 0
+        let src = "MAXIMIZE\nx + y\nSUBJECT TO\nx + y <= 3\nBOUNDS\nx >= 2\nx <= 1\ny <= -1\n\
+                   VARIABLES\nx\ny\n";
+        assert_eq!(issues(src), vec![
+            (Severity::Warning, Some(7)),
+            (Severity::Error, Some(7)),
+            (Severity::Error, Some(8)),
+        ]);
+    }
+
+    #[test]
+    fn test_not_finite() {
+        let mut lp = Parser::parse_lp("MAXIMIZE\nx\nSUBJECT TO\nx <= 3\nBOUNDS\nVARIABLES\nx\n")
+            .unwrap();
+        lp.ineqs[0].prods[0].0 = f64::NAN;
+        lp.ineqs[0].cst = f64::INFINITY;
+        let found = validate(&lp, None);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].to_string(), "the coefficient of x in constraint 1 is NaN");
+        assert_eq!(found[1].to_string(), "the right-hand side of constraint 1 is inf");
+    }
+}